@@ -3,7 +3,9 @@ use clap::Parser;
 use enum_dispatch::enum_dispatch;
 use std::{fmt::Display, str::FromStr};
 
-use crate::{parse_input_file, process_b64decode, process_b64encode, CmdExcutor};
+use crate::{
+    get_reader, get_writer, parse_input_file, process_b64decode, process_b64encode, CmdExcutor,
+};
 #[derive(Debug, Parser)]
 pub struct Base64Opts {
     #[command(subcommand)]
@@ -23,16 +25,32 @@ pub enum Base64SubCommand {
 pub struct Base64EncodeOpts {
     #[arg(short, long, value_parser=parse_input_file, default_value="-", help = "input file path, or '-' for stdin")]
     pub input: String,
-    #[arg(long, default_value = "standard", value_parser=Base64Format::from_str, help = "base64 format: [standard, urlsafe, nopadding]")]
+    #[arg(long, default_value = "standard", value_parser=parse_encode_format, help = "base64 format: [standard, urlsafe, nopadding]")]
     pub format: Base64Format,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), help = "wrap encoded lines after this many characters, e.g. 76 for MIME")]
+    pub wrap: Option<u64>,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
 }
 
 #[derive(Debug, Parser)]
 pub struct Base64DecodeOpts {
     #[arg(short, long, value_parser=parse_input_file, default_value="-", help = "input file path, or '-' for stdin")]
     pub input: String,
-    #[arg(long, value_parser=Base64Format::from_str, default_value = "standard", help = "base64 format: [standard, urlsafe, nopadding]")]
+    #[arg(long, value_parser=Base64Format::from_str, default_value = "standard", help = "base64 format: [standard, urlsafe, nopadding, auto], auto detects the alphabet and padding, whitespace is ignored")]
     pub format: Base64Format,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -40,6 +58,7 @@ pub enum Base64Format {
     Standard,
     UrlSafe,
     NoPadding,
+    Auto,
 }
 
 impl FromStr for Base64Format {
@@ -50,17 +69,29 @@ impl FromStr for Base64Format {
             "standard" => Ok(Base64Format::Standard),
             "urlsafe" => Ok(Base64Format::UrlSafe),
             "nopadding" => Ok(Base64Format::NoPadding),
+            "auto" => Ok(Base64Format::Auto),
             v => Err(anyhow::anyhow!("invalid base64 format: {}", v)),
         }
     }
 }
 
+// auto 只用于解码时检测格式, 编码时必须指定格式
+fn parse_encode_format(s: &str) -> Result<Base64Format> {
+    match s.parse()? {
+        Base64Format::Auto => Err(anyhow::anyhow!(
+            "auto is only supported when decoding, use standard, urlsafe or nopadding"
+        )),
+        format => Ok(format),
+    }
+}
+
 impl From<Base64Format> for &'static str {
     fn from(f: Base64Format) -> Self {
         match f {
             Base64Format::Standard => "standard",
             Base64Format::UrlSafe => "urlsafe",
             Base64Format::NoPadding => "nopadding",
+            Base64Format::Auto => "auto",
         }
     }
 }
@@ -73,17 +104,27 @@ impl Display for Base64Format {
 
 impl CmdExcutor for Base64EncodeOpts {
     async fn execute(self) -> Result<()> {
-        let ret = process_b64encode(&self.input, self.format)?;
-        println!("{}", ret);
+        process_b64encode(
+            get_reader(&self.input)?,
+            get_writer(&self.output)?,
+            self.format,
+            self.wrap.map(|w| w as usize),
+        )?;
+        // 输出到终端时以换行结尾, 写入文件时保持原样
+        if self.output == "-" {
+            println!();
+        }
         Ok(())
     }
 }
 
 impl CmdExcutor for Base64DecodeOpts {
     async fn execute(self) -> Result<()> {
-        let ret = process_b64decode(&self.input, self.format)?;
-        println!("{}", ret);
-        Ok(())
+        process_b64decode(
+            get_reader(&self.input)?,
+            get_writer(&self.output)?,
+            self.format,
+        )
     }
 }
 
@@ -1,27 +1,644 @@
-use crate::{parse_input_file, process_csv, CmdExcutor};
+use crate::{
+    get_content, get_reader, get_writer, parse_input_file, process_csv, process_csv_diff,
+    process_csv_group_by, process_csv_join, process_csv_mask, process_csv_merge,
+    process_csv_parquet, process_csv_query, process_csv_reverse, process_csv_split,
+    process_csv_stats, process_csv_validate, process_csv_xlsx, Aggregate, CmdExcutor,
+    ConvertOptions, CsvReaderOptions, Expr, Masker, Query, SortKey, SplitMode,
+};
 
 use anyhow::Result;
-use clap::Parser;
-use std::{fmt::Display, str::FromStr};
+use clap::{ArgAction, ArgGroup, Args, Parser};
+use encoding_rs::Encoding;
+use enum_dispatch::enum_dispatch;
+use std::{fmt::Display, path::Path, str::FromStr};
 
+// 不带子命令时, 保持 `rcli csv --input in.csv` 的 csv 转换用法
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(long, default_value_t = true)]
-    pub header: bool,
-    #[arg(short, long, default_value_t = ',')]
-    pub delimiter: char,
-    #[arg(short, long, required = true, value_parser=parse_input_file)]
+    #[command(subcommand)]
+    pub subcmd: Option<CsvSubCommand>,
+    #[command(flatten)]
+    pub convert: CsvConvertOpts,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExcutor)]
+pub enum CsvSubCommand {
+    #[command(
+        name = "reverse",
+        about = "convert json/yaml/ndjson records back to csv"
+    )]
+    Reverse(CsvReverseOpts),
+    #[command(
+        name = "query",
+        about = "select, rename, filter, sort and paginate csv records"
+    )]
+    Query(Box<CsvQueryOpts>),
+    #[command(
+        name = "stats",
+        about = "per-column statistics, or group-by aggregates"
+    )]
+    Stats(CsvStatsOpts),
+    #[command(
+        name = "validate",
+        about = "validate csv records against a schema, report violations as json"
+    )]
+    Validate(CsvValidateOpts),
+    #[command(
+        name = "join",
+        about = "join two csv files on key columns (inner, left or outer)"
+    )]
+    Join(CsvJoinOpts),
+    #[command(
+        name = "diff",
+        about = "report added, removed and changed rows between two csv files"
+    )]
+    Diff(CsvDiffOpts),
+    #[command(
+        name = "split",
+        about = "split csv into files by row count, byte size or column value"
+    )]
+    Split(CsvSplitOpts),
+    #[command(
+        name = "merge",
+        about = "concatenate csv files with compatible headers"
+    )]
+    Merge(CsvMergeOpts),
+    #[command(
+        name = "mask",
+        about = "anonymize csv columns by keyed hash, redaction, format-preserving or fake values"
+    )]
+    Mask(CsvMaskOpts),
+}
+
+#[derive(Debug, Args)]
+pub struct CsvConvertOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    // 有子命令时不需要 input, 因此这里是 Option, 由 clap 保证转换时一定存在
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv/xlsx/parquet file path, or '-' for stdin")]
+    pub input: Option<String>,
+    #[arg(long, value_parser=SourceFormat::from_str, help = "input format: [csv, xlsx, parquet] [default: detected from the file extension]")]
+    pub from: Option<SourceFormat>,
+    #[arg(long, help = "xlsx sheet name or 0-based index [default: first sheet]")]
+    pub sheet: Option<String>,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str, help = "output format: [json, ndjson, yaml, toml, xml, markdown, table]")]
+    pub format: OutputFormat,
+    #[command(flatten)]
+    pub record: CsvRecordOpts,
+}
+
+// 记录的类型转换和输出形式, 供 csv 转换和查询复用
+#[derive(Debug, Args)]
+pub struct CsvRecordOpts {
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "infer integer/float/boolean/date column types, empty cells become null"
+    )]
+    pub infer: bool,
+    #[arg(long, value_parser=parse_input_file, help = "yaml/json file pinning column types, e.g. `age: integer`")]
+    pub schema: Option<String>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "build nested objects/arrays from headers like `address.city` or `tags[0]`"
+    )]
+    pub nested: bool,
+}
+
+impl From<CsvRecordOpts> for ConvertOptions {
+    fn from(opts: CsvRecordOpts) -> Self {
+        Self {
+            infer: opts.infer,
+            schema: opts.schema,
+            nested: opts.nested,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvQueryOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub record: CsvRecordOpts,
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv file path, or '-' for stdin")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str, help = "output format: [json, ndjson, yaml, toml, xml, markdown, table]")]
+    pub format: OutputFormat,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "columns to output, in order"
+    )]
+    pub select: Vec<String>,
+    #[arg(long, value_delimiter = ',', value_parser = parse_rename, help = "rename columns, e.g. `Kit Number:kit`")]
+    pub rename: Vec<(String, String)>,
+    #[arg(short = 'w', long = "where", value_parser = Expr::from_str, help = "filter expression, e.g. `age > 30 && nationality == \"Italy\"`")]
+    pub filter: Option<Expr>,
+    #[arg(long, value_delimiter = ',', value_parser = SortKey::from_str, help = "sort columns, e.g. `age:desc,name`")]
+    pub sort: Vec<SortKey>,
+    #[arg(long, default_value_t = 0, help = "number of records to skip")]
+    pub offset: usize,
+    #[arg(short, long, help = "max number of records to output")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvStatsOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv file path, or '-' for stdin")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str, help = "output format: [json, ndjson, yaml, toml, xml, markdown, table]")]
+    pub format: OutputFormat,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "number of most frequent values to report per column"
+    )]
+    pub top: usize,
+    #[arg(long, value_delimiter = ',', help = "group records by these columns")]
+    pub group_by: Vec<String>,
+    #[arg(long, value_delimiter = ',', value_parser = Aggregate::from_str, requires = "group_by", help = "aggregates per group, e.g. `count,sum:salary,avg:age` [default: count]")]
+    pub agg: Vec<Aggregate>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvValidateOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv file path, or '-' for stdin")]
     pub input: String,
-    #[arg(short, long)]
-    pub output: Option<String>,
-    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str)]
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "report output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, value_parser=parse_input_file, help = "yaml/json schema with required, type, pattern, enum, unique, min and max rules")]
+    pub schema: String,
+    #[arg(long, help = "max number of violations listed in the report")]
+    pub max_errors: Option<usize>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(long, value_parser=parse_input_file, help = "left csv file path, or '-' for stdin")]
+    pub left: String,
+    #[arg(long, value_parser=parse_input_file, help = "right csv file path, or '-' for stdin")]
+    pub right: String,
+    #[arg(
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "key columns present in both files"
+    )]
+    pub on: Vec<String>,
+    #[arg(long, default_value = "inner", value_parser=JoinKind::from_str, help = "join type: [inner, left, outer]")]
+    pub how: JoinKind,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str, help = "output format: [json, ndjson, yaml, toml, xml, markdown, table]")]
     pub format: OutputFormat,
 }
 
+#[derive(Debug, Parser)]
+pub struct CsvDiffOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(long, value_parser=parse_input_file, help = "old csv file path, or '-' for stdin")]
+    pub old: String,
+    #[arg(long, value_parser=parse_input_file, help = "new csv file path, or '-' for stdin")]
+    pub new: String,
+    #[arg(
+        short,
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "key columns identifying a row"
+    )]
+    pub key: Vec<String>,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=OutputFormat::from_str, help = "output format: [json, ndjson, yaml, toml, xml, markdown, table]")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("mode").required(true).args(["rows", "bytes", "by"])))]
+pub struct CsvSplitOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv file path, or '-' for stdin")]
+    pub input: String,
+    #[arg(long, help = "max number of records per file")]
+    pub rows: Option<usize>,
+    #[arg(long, value_parser = parse_size, help = "max size per file, e.g. 500K, 10M, 1G")]
+    pub bytes: Option<u64>,
+    #[arg(long, help = "write records into one file per value of this column")]
+    pub by: Option<String>,
+    #[arg(long, default_value = ".", help = "directory for the split files")]
+    pub out_dir: String,
+    #[arg(
+        long,
+        help = "file name prefix [default: input file name, or `part` for stdin]"
+    )]
+    pub prefix: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMergeOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(required = true, value_parser=parse_input_file, help = "csv files to merge, '-' for stdin")]
+    pub inputs: Vec<String>,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "union mismatched headers, missing cells are left empty"
+    )]
+    pub union: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMaskOpts {
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input csv file path, or '-' for stdin")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(short, long, required = true, value_delimiter = ',', value_parser = parse_mask_column, help = "columns to mask, optionally with their own method, e.g. `email,phone:preserve`")]
+    pub columns: Vec<(String, Option<MaskMethod>)>,
+    #[arg(long, default_value = "hash", value_parser = MaskMethod::from_str, help = "default mask method: [hash, redact, preserve, fake]")]
+    pub method: MaskMethod,
+    #[arg(short, long, value_parser=parse_input_file, help = "32-byte blake3 key file, without it a random key is used and output differs between runs")]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvReverseOpts {
+    #[arg(short, long, required = true, value_parser=parse_input_file, help = "input file path, or '-' for stdin")]
+    pub input: String,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(long, default_value = "json", value_parser=InputFormat::from_str, help = "input format: [json, yaml, ndjson]")]
+    pub format: InputFormat,
+    #[arg(short, long, default_value = ",", value_parser = parse_csv_byte, help = "field delimiter of the output csv")]
+    pub delimiter: u8,
+}
+
+// csv 解析相关的参数, 供各个 csv 子命令复用
+#[derive(Debug, Args)]
+pub struct CsvReadOpts {
+    #[arg(long, default_value_t = true, num_args = 0..=1, default_missing_value = "true", action = ArgAction::Set, help = "whether the first row is a header, headerless files get col_0, col_1, ...")]
+    pub header: bool,
+    #[arg(short, long, default_value = ",", value_parser = parse_csv_byte, help = "field delimiter, e.g. ',', ';' or '\\t'")]
+    pub delimiter: u8,
+    #[arg(long, default_value = "\"", value_parser = parse_csv_byte, help = "quote character")]
+    pub quote: u8,
+    #[arg(long, value_parser = parse_csv_byte, help = "escape character for quotes inside quoted fields, e.g. '\\'")]
+    pub escape: Option<u8>,
+    #[arg(long, value_parser = parse_csv_byte, help = "lines starting with this character are skipped")]
+    pub comment: Option<u8>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "allow records with a varying number of fields"
+    )]
+    pub flexible: bool,
+    #[arg(long, default_value = "auto", value_parser = CsvEncoding::from_str, help = "input encoding, e.g. utf-8, utf-16le, gbk, latin1; auto detects BOM, utf-8 and legacy encodings")]
+    pub encoding: CsvEncoding,
+}
+
+impl From<&CsvReadOpts> for CsvReaderOptions {
+    fn from(opts: &CsvReadOpts) -> Self {
+        Self {
+            delimiter: opts.delimiter,
+            has_headers: opts.header,
+            quote: opts.quote,
+            escape: opts.escape,
+            comment: opts.comment,
+            flexible: opts.flexible,
+            encoding: opts.encoding.into(),
+        }
+    }
+}
+
+fn parse_rename(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!("expect `old:new`, got: {}", s)),
+    }
+}
+
+// `列名` 或 `列名:方法`, 冒号后不是合法的方法时整体作为列名
+fn parse_mask_column(s: &str) -> Result<(String, Option<MaskMethod>), String> {
+    match s.rsplit_once(':') {
+        Some((column, method)) if !column.is_empty() => match method.parse() {
+            Ok(method) => Ok((column.to_string(), Some(method))),
+            Err(_) => Ok((s.to_string(), None)),
+        },
+        _ if s.is_empty() => Err("column name must not be empty".to_string()),
+        _ => Ok((s.to_string(), None)),
+    }
+}
+
+// 文件大小, 支持 K/M/G 后缀 (1024 进制), 例如 500K, 10MB
+fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_uppercase();
+    let digits = upper.trim_end_matches('B');
+    let (number, unit) = match digits.char_indices().last() {
+        Some((i, 'K')) => (&digits[..i], 1 << 10),
+        Some((i, 'M')) => (&digits[..i], 1 << 20),
+        Some((i, 'G')) => (&digits[..i], 1 << 30),
+        _ => (digits, 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("invalid size: {}, expect e.g. 500K or 10M", s))
+}
+
+// csv 的分隔符/引号等只支持单个 ascii 字符, 额外支持 \t 和 tab 的写法
+fn parse_csv_byte(s: &str) -> Result<u8, String> {
+    match s {
+        "\\t" | "tab" => Ok(b'\t'),
+        s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        s => Err(format!("expect a single ascii character, got: {}", s)),
+    }
+}
+
+// csv 输入的编码, 支持 encoding_rs 认识的所有标签
+#[derive(Debug, Clone, Copy)]
+pub enum CsvEncoding {
+    Auto,
+    Label(&'static Encoding),
+}
+
+impl FromStr for CsvEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(CsvEncoding::Auto),
+            v => Encoding::for_label(v.as_bytes())
+                .map(CsvEncoding::Label)
+                .ok_or_else(|| anyhow::anyhow!("invalid encoding: {}", v)),
+        }
+    }
+}
+
+impl From<CsvEncoding> for Option<&'static Encoding> {
+    fn from(e: CsvEncoding) -> Self {
+        match e {
+            CsvEncoding::Auto => None,
+            CsvEncoding::Label(encoding) => Some(encoding),
+        }
+    }
+}
+
+impl Display for CsvEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvEncoding::Auto => write!(f, "auto"),
+            CsvEncoding::Label(encoding) => write!(f, "{}", encoding.name()),
+        }
+    }
+}
+
+// mask 子命令的脱敏方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskMethod {
+    Hash,
+    Redact,
+    Preserve,
+    Fake,
+}
+
+impl FromStr for MaskMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hash" => Ok(MaskMethod::Hash),
+            "redact" => Ok(MaskMethod::Redact),
+            "preserve" | "format" => Ok(MaskMethod::Preserve),
+            "fake" => Ok(MaskMethod::Fake),
+            v => Err(anyhow::anyhow!("invalid mask method: {}", v)),
+        }
+    }
+}
+
+impl From<MaskMethod> for &'static str {
+    fn from(m: MaskMethod) -> Self {
+        match m {
+            MaskMethod::Hash => "hash",
+            MaskMethod::Redact => "redact",
+            MaskMethod::Preserve => "preserve",
+            MaskMethod::Fake => "fake",
+        }
+    }
+}
+
+impl Display for MaskMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+// csv 转换支持的表格输入格式, xlsx 也可以是 xls/ods
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Csv,
+    Xlsx,
+    Parquet,
+}
+
+impl SourceFormat {
+    // 根据文件扩展名判断输入格式, 无法判断时 (包括 stdin) 按 csv 处理
+    pub fn detect(input: &str) -> Self {
+        let extension = Path::new(input)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => SourceFormat::Xlsx,
+            Some("parquet" | "pq") => SourceFormat::Parquet,
+            _ => SourceFormat::Csv,
+        }
+    }
+}
+
+impl FromStr for SourceFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(SourceFormat::Csv),
+            "xlsx" | "excel" => Ok(SourceFormat::Xlsx),
+            "parquet" => Ok(SourceFormat::Parquet),
+            v => Err(anyhow::anyhow!("invalid input format: {}", v)),
+        }
+    }
+}
+
+impl From<SourceFormat> for &'static str {
+    fn from(f: SourceFormat) -> Self {
+        match f {
+            SourceFormat::Csv => "csv",
+            SourceFormat::Xlsx => "xlsx",
+            SourceFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl Display for SourceFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+// reverse 子命令支持的输入格式
+#[derive(Debug, Clone, Copy)]
+pub enum InputFormat {
+    Json,
+    Yaml,
+    Ndjson,
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(InputFormat::Json),
+            "yaml" => Ok(InputFormat::Yaml),
+            "ndjson" => Ok(InputFormat::Ndjson),
+            v => Err(anyhow::anyhow!("invalid input format: {}", v)),
+        }
+    }
+}
+
+impl From<InputFormat> for &'static str {
+    fn from(f: InputFormat) -> Self {
+        match f {
+            InputFormat::Json => "json",
+            InputFormat::Yaml => "yaml",
+            InputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+// join 子命令的连接方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Outer,
+}
+
+impl FromStr for JoinKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "inner" => Ok(JoinKind::Inner),
+            "left" => Ok(JoinKind::Left),
+            "outer" | "full" => Ok(JoinKind::Outer),
+            v => Err(anyhow::anyhow!("invalid join type: {}", v)),
+        }
+    }
+}
+
+impl From<JoinKind> for &'static str {
+    fn from(k: JoinKind) -> Self {
+        match k {
+            JoinKind::Inner => "inner",
+            JoinKind::Left => "left",
+            JoinKind::Outer => "outer",
+        }
+    }
+}
+
+impl Display for JoinKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Json,
+    Ndjson,
     Yaml,
+    Toml,
+    Xml,
+    Markdown,
+    Table,
 }
 
 impl FromStr for OutputFormat {
@@ -30,7 +647,12 @@ impl FromStr for OutputFormat {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
             "yaml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            "xml" => Ok(OutputFormat::Xml),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "table" => Ok(OutputFormat::Table),
             v => Err(anyhow::anyhow!("invalid output format: {}", v)),
         }
     }
@@ -40,7 +662,12 @@ impl From<OutputFormat> for &'static str {
     fn from(f: OutputFormat) -> Self {
         match f {
             OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Table => "table",
         }
     }
 }
@@ -53,9 +680,259 @@ impl Display for OutputFormat {
 
 impl CmdExcutor for CsvOpts {
     async fn execute(self) -> Result<()> {
-        let output = self
-            .output
-            .unwrap_or_else(|| format!("output.{}", self.format));
-        process_csv(&self.input, output, self.format)
+        match self.subcmd {
+            Some(subcmd) => subcmd.execute().await,
+            None => self.convert.execute().await,
+        }
+    }
+}
+
+impl CsvConvertOpts {
+    // xlsx 和 parquet 的单元格本身带有类型, 也不需要按 csv 的方式解析, 这些选项不会生效
+    fn unsupported_options(&self, from: SourceFormat) -> Vec<&'static str> {
+        let read = &self.read;
+        let csv_only = [
+            ("--infer", self.record.infer),
+            ("--delimiter", read.delimiter != b','),
+            ("--quote", read.quote != b'"'),
+            ("--escape", read.escape.is_some()),
+            ("--comment", read.comment.is_some()),
+            ("--flexible", read.flexible),
+            ("--encoding", !matches!(read.encoding, CsvEncoding::Auto)),
+        ];
+        let options = match from {
+            SourceFormat::Csv => vec![("--sheet", self.sheet.is_some())],
+            SourceFormat::Xlsx => csv_only.to_vec(),
+            SourceFormat::Parquet => csv_only
+                .into_iter()
+                .chain([
+                    ("--header", !read.header),
+                    ("--sheet", self.sheet.is_some()),
+                ])
+                .collect(),
+        };
+        options
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+impl CmdExcutor for CsvConvertOpts {
+    async fn execute(self) -> Result<()> {
+        let input = self
+            .input
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("the input file is required"))?;
+        let from = self.from.unwrap_or_else(|| SourceFormat::detect(input));
+        let unsupported = self.unsupported_options(from);
+        if !unsupported.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} not supported for {} input",
+                unsupported.join(", "),
+                from
+            ));
+        }
+        let writer = get_writer(&self.output)?;
+        match from {
+            SourceFormat::Csv => process_csv(
+                input,
+                writer,
+                self.format,
+                (&self.read).into(),
+                self.record.into(),
+            ),
+            SourceFormat::Xlsx => process_csv_xlsx(
+                input,
+                writer,
+                self.format,
+                self.sheet.as_deref(),
+                self.read.header,
+                self.record.into(),
+            ),
+            SourceFormat::Parquet => {
+                process_csv_parquet(input, writer, self.format, self.record.into())
+            }
+        }
+    }
+}
+
+impl CmdExcutor for CsvQueryOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        let query = Query {
+            select: self.select,
+            rename: self.rename,
+            filter: self.filter,
+            sort: self.sort,
+            offset: self.offset,
+            limit: self.limit,
+        };
+        process_csv_query(
+            &self.input,
+            writer,
+            self.format,
+            (&self.read).into(),
+            self.record.into(),
+            query,
+        )
+    }
+}
+
+impl CmdExcutor for CsvStatsOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        let options = (&self.read).into();
+        if self.group_by.is_empty() {
+            return process_csv_stats(&self.input, writer, self.format, options, self.top);
+        }
+        let aggregates = if self.agg.is_empty() {
+            vec![Aggregate::Count]
+        } else {
+            self.agg
+        };
+        process_csv_group_by(
+            &self.input,
+            writer,
+            self.format,
+            options,
+            &self.group_by,
+            &aggregates,
+        )
+    }
+}
+
+impl CmdExcutor for CsvValidateOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        process_csv_validate(
+            &self.input,
+            writer,
+            (&self.read).into(),
+            &self.schema,
+            self.max_errors,
+        )
+    }
+}
+
+impl CmdExcutor for CsvJoinOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        process_csv_join(
+            &self.left,
+            &self.right,
+            writer,
+            self.format,
+            (&self.read).into(),
+            &self.on,
+            self.how,
+        )
+    }
+}
+
+impl CmdExcutor for CsvDiffOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        process_csv_diff(
+            &self.old,
+            &self.new,
+            writer,
+            self.format,
+            (&self.read).into(),
+            &self.key,
+        )
+    }
+}
+
+impl CmdExcutor for CsvSplitOpts {
+    async fn execute(self) -> Result<()> {
+        let mode = match (self.rows, self.bytes, self.by) {
+            (Some(rows), _, _) => SplitMode::Rows(rows),
+            (_, Some(bytes), _) => SplitMode::Bytes(bytes),
+            (_, _, Some(column)) => SplitMode::Column(column),
+            _ => unreachable!("clap requires one of --rows, --bytes and --by"),
+        };
+        let prefix = self.prefix.unwrap_or_else(|| {
+            Path::new(&self.input)
+                .file_stem()
+                .filter(|_| self.input != "-")
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "part".to_string())
+        });
+        let paths = process_csv_split(
+            &self.input,
+            (&self.read).into(),
+            mode,
+            Path::new(&self.out_dir),
+            &prefix,
+        )?;
+        for path in paths {
+            println!("{}", path.display());
+        }
+        Ok(())
+    }
+}
+
+impl CmdExcutor for CsvMergeOpts {
+    async fn execute(self) -> Result<()> {
+        let writer = get_writer(&self.output)?;
+        process_csv_merge(&self.inputs, writer, (&self.read).into(), self.union)
+    }
+}
+
+impl CmdExcutor for CsvMaskOpts {
+    async fn execute(self) -> Result<()> {
+        let masker = match &self.key {
+            Some(path) => Masker::try_new(get_content(path)?)?,
+            None => Masker::new(rand::random()),
+        };
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|(column, method)| (column, method.unwrap_or(self.method)))
+            .collect::<Vec<_>>();
+        let writer = get_writer(&self.output)?;
+        process_csv_mask(&self.input, writer, (&self.read).into(), &columns, masker)
+    }
+}
+
+impl CmdExcutor for CsvReverseOpts {
+    async fn execute(self) -> Result<()> {
+        let reader = get_reader(&self.input)?;
+        let writer = get_writer(&self.output)?;
+        process_csv_reverse(reader, writer, self.format, self.delimiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_byte() {
+        assert_eq!(parse_csv_byte(";"), Ok(b';'));
+        assert_eq!(parse_csv_byte("\\t"), Ok(b'\t'));
+        assert!(parse_csv_byte("ab").is_err());
+        assert!(parse_csv_byte("，").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_size("10mb"), Ok(10 * 1024 * 1024));
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_parse_mask_column() {
+        assert_eq!(
+            parse_mask_column("phone:preserve"),
+            Ok(("phone".to_string(), Some(MaskMethod::Preserve)))
+        );
+        assert_eq!(parse_mask_column("a:b"), Ok(("a:b".to_string(), None)));
+        assert_eq!(parse_mask_column("email"), Ok(("email".to_string(), None)));
     }
 }
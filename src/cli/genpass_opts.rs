@@ -1,10 +1,18 @@
 use anyhow::Result;
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::{fmt::Display, str::FromStr};
 
-use crate::{process_genpass, CmdExcutor};
-use zxcvbn::zxcvbn;
+use crate::{
+    get_reader, get_writer, parse_input_file, process_genpass, process_genpass_check,
+    process_genpass_token, process_passphrase, write_password_reports, CmdExcutor, Generator,
+    Passphrase,
+};
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenpassOpts {
+    #[command(subcommand)]
+    pub subcmd: Option<GenpassSubCommand>,
     #[arg(long, default_value_t = false)]
     pub no_upper: bool,
     #[arg(long, default_value_t = false)]
@@ -14,22 +22,292 @@ pub struct GenpassOpts {
     #[arg(long, default_value_t = false)]
     pub no_symbol: bool,
     #[arg(short, long, default_value_t = 16)]
-    pub length: u8,
+    pub length: usize,
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "words", "pattern", "pronounceable", "min_score", "length", "no_upper", "no_lower",
+            "no_number", "no_symbol", "symbols", "include", "exclude", "exclude_ambiguous",
+            "min_upper", "min_lower", "min_digits", "min_symbols",
+        ],
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "generate a token of this many random bytes instead of a password"
+    )]
+    pub bytes: Option<usize>,
+    #[arg(long, default_value = "hex", value_parser = TokenEncoding::from_str, requires = "bytes", help = "token encoding: [hex, base64, base32], base64 is url safe without padding")]
+    pub encoding: TokenEncoding,
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        value_parser = parse_ascii,
+        help = "symbol set to use instead of the default !@#$%^&*?~"
+    )]
+    pub symbols: Option<String>,
+    #[arg(
+        long,
+        default_value = "",
+        allow_hyphen_values = true,
+        value_parser = parse_ascii,
+        help = "extra characters to add to the character set"
+    )]
+    pub include: String,
+    #[arg(
+        long,
+        default_value = "",
+        allow_hyphen_values = true,
+        value_parser = parse_ascii,
+        help = "characters to remove from the character set"
+    )]
+    pub exclude: String,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "exclude ambiguous characters: iIlLoO01"
+    )]
+    pub exclude_ambiguous: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["words", "length", "min_upper", "min_lower", "min_digits", "min_symbols"],
+        help = "generate from a template: c/C consonant, v/V vowel, l/L letter, 9 digit, s symbol, * any, '\\' escapes, e.g. Cvcc-9999-CVC"
+    )]
+    pub pattern: Option<String>,
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["words", "pattern"],
+        help = "generate a pronounceable password of alternating consonants and vowels"
+    )]
+    pub pronounceable: bool,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "minimum number of uppercase letters"
+    )]
+    pub min_upper: usize,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "minimum number of lowercase letters"
+    )]
+    pub min_lower: usize,
+    #[arg(long, default_value_t = 0, help = "minimum number of digits")]
+    pub min_digits: usize,
+    #[arg(long, default_value_t = 0, help = "minimum number of symbols")]
+    pub min_symbols: usize,
+    #[arg(
+        short,
+        long,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        help = "generate a passphrase of this many words from the EFF wordlist instead of random characters"
+    )]
+    pub words: Option<usize>,
+    #[arg(
+        long,
+        default_value = "-",
+        requires = "words",
+        help = "separator between passphrase words"
+    )]
+    pub separator: String,
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "words",
+        help = "capitalize each passphrase word"
+    )]
+    pub capitalize: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "words",
+        help = "append a random digit to the passphrase"
+    )]
+    pub append_digit: bool,
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "words",
+        help = "append a random symbol to the passphrase"
+    )]
+    pub append_symbol: bool,
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        help = "number of passwords to generate"
+    )]
+    pub count: usize,
+    #[arg(long, default_value = "plain", value_parser = GenpassFormat::from_str, help = "output format: [plain, json, csv], json and csv include score, crack time and entropy")]
+    pub format: GenpassFormat,
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "output file path, or '-' for stdout"
+    )]
+    pub output: String,
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=4),
+        help = "minimum zxcvbn score (0-4), passwords are regenerated until it is met"
+    )]
+    pub min_score: u8,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExcutor)]
+pub enum GenpassSubCommand {
+    #[command(
+        name = "check",
+        about = "Check the strength of passwords, one per line."
+    )]
+    Check(GenpassCheckOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenpassCheckOpts {
+    #[arg(short, long, value_parser=parse_input_file, default_value="-", help = "input file path, or '-' for stdin")]
+    pub input: String,
+}
+
+// 字符集按字节选取, 只接受 ascii 字符
+fn parse_ascii(s: &str) -> Result<String, String> {
+    if s.is_ascii() {
+        Ok(s.to_string())
+    } else {
+        Err(format!("only ascii characters are supported: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenEncoding {
+    Hex,
+    Base64,
+    Base32,
+}
+
+impl FromStr for TokenEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hex" => Ok(TokenEncoding::Hex),
+            "base64" => Ok(TokenEncoding::Base64),
+            "base32" => Ok(TokenEncoding::Base32),
+            v => Err(anyhow::anyhow!("invalid token encoding: {}", v)),
+        }
+    }
+}
+
+impl From<TokenEncoding> for &'static str {
+    fn from(e: TokenEncoding) -> Self {
+        match e {
+            TokenEncoding::Hex => "hex",
+            TokenEncoding::Base64 => "base64",
+            TokenEncoding::Base32 => "base32",
+        }
+    }
+}
+
+impl Display for TokenEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenpassFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+impl FromStr for GenpassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(GenpassFormat::Plain),
+            "json" => Ok(GenpassFormat::Json),
+            "csv" => Ok(GenpassFormat::Csv),
+            v => Err(anyhow::anyhow!("invalid genpass format: {}", v)),
+        }
+    }
+}
+
+impl From<GenpassFormat> for &'static str {
+    fn from(f: GenpassFormat) -> Self {
+        match f {
+            GenpassFormat::Plain => "plain",
+            GenpassFormat::Json => "json",
+            GenpassFormat::Csv => "csv",
+        }
+    }
+}
+
+impl Display for GenpassFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
 }
 
 impl CmdExcutor for GenpassOpts {
     async fn execute(self) -> Result<()> {
-        let ret = process_genpass(
-            self.no_upper,
-            self.no_lower,
-            self.no_number,
-            self.no_symbol,
-            self.length,
-        );
-        let ret = String::from_utf8(ret)?;
-        println!("{}", ret);
-        let estimate = zxcvbn(&ret, &[])?;
-        eprintln!("estimate: {:?}", estimate.score());
+        if let Some(subcmd) = self.subcmd {
+            return subcmd.execute().await;
+        }
+        let reports = match (self.bytes, self.words) {
+            (Some(bytes), _) => process_genpass_token(bytes, self.encoding, self.count)?,
+            (None, Some(words)) => {
+                let mut passphrase = Passphrase::new(words)
+                    .separator(self.separator)
+                    .capitalize(self.capitalize)
+                    .digit(self.append_digit)
+                    .symbol(self.append_symbol);
+                if let Some(symbols) = &self.symbols {
+                    passphrase = passphrase.symbols(symbols);
+                }
+                process_passphrase(&passphrase, self.count, self.min_score)?
+            }
+            (None, None) => {
+                let mut generator = Generator::new()
+                    .no_upper(self.no_upper)
+                    .no_lower(self.no_lower)
+                    .no_number(self.no_number)
+                    .no_symbol(self.no_symbol)
+                    .include(&self.include)
+                    .exclude(&self.exclude)
+                    .exclude_ambiguous(self.exclude_ambiguous)
+                    .pronounceable(self.pronounceable)
+                    .min_upper(self.min_upper)
+                    .min_lower(self.min_lower)
+                    .min_number(self.min_digits)
+                    .min_symbol(self.min_symbols);
+                if let Some(symbols) = &self.symbols {
+                    generator = generator.symbols(symbols);
+                }
+                if let Some(pattern) = &self.pattern {
+                    generator = generator.pattern(pattern);
+                }
+                process_genpass(&generator.build()?, self.length, self.count, self.min_score)?
+            }
+        };
+        write_password_reports(&reports, self.format, get_writer(&self.output)?)?;
+        // plain 格式不包含强度评估, 输出到 stderr
+        if self.format == GenpassFormat::Plain {
+            for report in &reports {
+                eprintln!(
+                    "estimate: {}, entropy: {:.1} bits",
+                    report.score, report.entropy
+                );
+            }
+        }
         Ok(())
     }
 }
+
+impl CmdExcutor for GenpassCheckOpts {
+    async fn execute(self) -> Result<()> {
+        process_genpass_check(get_reader(&self.input)?, get_writer("-")?)
+    }
+}
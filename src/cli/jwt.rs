@@ -118,9 +118,9 @@ impl CmdExcutor for JwtVerifyOpts {
             .required_claims
             .map_or(HashSet::from_iter(["exp".to_owned()]), HashSet::from_iter);
 
-        validation.validate_aud = self.validate_aud.map_or(true, |v| v);
-        validation.validate_exp = self.validate_exp.map_or(true, |v| v);
-        validation.validate_nbf = self.validate_nbf.map_or(false, |v| v);
+        validation.validate_aud = self.validate_aud.unwrap_or(true);
+        validation.validate_exp = self.validate_exp.unwrap_or(true);
+        validation.validate_nbf = self.validate_nbf.unwrap_or(false);
         validation.iss = self.iss.map(HashSet::from_iter);
         validation.aud = self.aud.map(HashSet::from_iter);
         validation.sub = self.sub;
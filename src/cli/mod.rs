@@ -3,6 +3,7 @@ mod csv_opts;
 mod genpass_opts;
 mod http_serve;
 mod jwt;
+mod otp;
 mod text;
 
 use anyhow::Result;
@@ -15,10 +16,19 @@ use std::time::Duration;
 pub use self::base64_opts::{
     Base64DecodeOpts, Base64EncodeOpts, Base64Format, Base64Opts, Base64SubCommand,
 };
-pub use self::csv_opts::{CsvOpts, OutputFormat};
-pub use self::genpass_opts::GenpassOpts;
+pub use self::csv_opts::{
+    CsvConvertOpts, CsvDiffOpts, CsvEncoding, CsvJoinOpts, CsvMaskOpts, CsvMergeOpts, CsvOpts,
+    CsvQueryOpts, CsvReadOpts, CsvRecordOpts, CsvReverseOpts, CsvSplitOpts, CsvStatsOpts,
+    CsvSubCommand, CsvValidateOpts, InputFormat, JoinKind, MaskMethod, OutputFormat, SourceFormat,
+};
+pub use self::genpass_opts::{
+    GenpassCheckOpts, GenpassFormat, GenpassOpts, GenpassSubCommand, TokenEncoding,
+};
 pub use self::http_serve::{HttpOpts, HttpServeOpts, HttpSubCommand};
 pub use self::jwt::{JwtOpts, JwtSignOpts, JwtSubCommand, JwtVerifyOpts};
+pub use self::otp::{
+    OtpAlgorithm, OtpCodeOpts, OtpGenerateOpts, OtpOpts, OtpParamOpts, OtpSubCommand, OtpVerifyOpts,
+};
 pub use self::text::{
     TextDecryptOpts, TextEncryptOpts, TextKeyGenerateOpts, TextOpts, TextSignFormat, TextSignOpts,
     TextSubCommand, TextVerifyOpts,
@@ -38,10 +48,13 @@ pub struct Opts {
 pub enum SubCommand {
     // rcli csv --header xx -delimiter , -input /tmp/1.csv -output output.json
     #[command(name = "csv", about = "csv file processor")]
-    Csv(CsvOpts),
+    Csv(Box<CsvOpts>),
     // rcli genpass --upper xx --lower --symbol --number --length
     #[command(name = "genpass", about = "generate password")]
     Genpass(GenpassOpts),
+    // rcli otp generate/code/verify --secret --uri
+    #[command(name = "otp", about = "HOTP/TOTP one-time password")]
+    Otp(OtpOpts),
     // rcli base64 --encode/decode --output
     #[command(name = "base64", about = "base64 encode/decode")]
     Base64(Base64Opts),
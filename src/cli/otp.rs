@@ -0,0 +1,272 @@
+use anyhow::Result;
+use clap::{ArgGroup, Args, Parser};
+use enum_dispatch::enum_dispatch;
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{generate_otp_secret, get_content, parse_input_file, CmdExcutor, Otp};
+
+// --next 和 --skew 的上限, 窗口越大, 猜中 code 的概率越高
+const MAX_WINDOW: u64 = 100;
+
+#[derive(Debug, Parser)]
+pub struct OtpOpts {
+    #[command(subcommand)]
+    pub subcmd: OtpSubCommand,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExcutor)]
+pub enum OtpSubCommand {
+    #[command(
+        name = "generate",
+        about = "Generate a random base32 secret and its otpauth:// uri."
+    )]
+    Generate(OtpGenerateOpts),
+    #[command(name = "code", about = "Compute the current and next HOTP/TOTP codes.")]
+    Code(OtpCodeOpts),
+    #[command(
+        name = "verify",
+        about = "Verify a HOTP/TOTP code within a skew window."
+    )]
+    Verify(OtpVerifyOpts),
+}
+
+// 一次性密码的参数, 使用 --uri 时从 uri 中读取
+#[derive(Debug, Args)]
+pub struct OtpParamOpts {
+    #[arg(long, value_parser = OtpAlgorithm::from_str, default_value = "sha1", conflicts_with = "uri", help = "hmac algorithm: [sha1, sha256, sha512]")]
+    pub algorithm: OtpAlgorithm,
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(6..=8), conflicts_with = "uri", help = "number of digits")]
+    pub digits: u32,
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "uri", help = "totp period in seconds")]
+    pub period: u64,
+    #[arg(
+        long,
+        conflicts_with = "uri",
+        help = "use HOTP with this counter instead of TOTP"
+    )]
+    pub counter: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpGenerateOpts {
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(10..=64), help = "secret length in bytes")]
+    pub bytes: u64,
+    #[arg(
+        long,
+        default_value = "rcli",
+        help = "account name shown in the authenticator"
+    )]
+    pub label: String,
+    #[arg(long, help = "issuer shown in the authenticator")]
+    pub issuer: Option<String>,
+    #[arg(long, value_parser = OtpAlgorithm::from_str, default_value = "sha1", help = "hmac algorithm: [sha1, sha256, sha512]")]
+    pub algorithm: OtpAlgorithm,
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(6..=8), help = "number of digits")]
+    pub digits: u32,
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..), help = "totp period in seconds")]
+    pub period: u64,
+    #[arg(
+        long,
+        help = "generate a HOTP uri starting at this counter instead of TOTP"
+    )]
+    pub counter: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("key").required(true).args(["secret", "secret_file", "uri"])))]
+pub struct OtpCodeOpts {
+    #[arg(
+        short,
+        long,
+        help = "base32 secret, prefer --secret-file to keep it out of shell history"
+    )]
+    pub secret: Option<String>,
+    #[arg(long, value_parser = parse_input_file, help = "file containing the base32 secret, or '-' for stdin")]
+    pub secret_file: Option<String>,
+    #[arg(short, long, help = "otpauth:// uri")]
+    pub uri: Option<String>,
+    #[command(flatten)]
+    pub params: OtpParamOpts,
+    #[arg(
+        short,
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u64).range(0..=MAX_WINDOW),
+        help = "number of following codes to print"
+    )]
+    pub next: u64,
+}
+
+#[derive(Debug, Parser)]
+#[command(group(ArgGroup::new("key").required(true).args(["secret", "secret_file", "uri"])))]
+pub struct OtpVerifyOpts {
+    #[arg(
+        short,
+        long,
+        help = "base32 secret, prefer --secret-file to keep it out of shell history"
+    )]
+    pub secret: Option<String>,
+    #[arg(long, value_parser = parse_input_file, help = "file containing the base32 secret, or '-' for stdin")]
+    pub secret_file: Option<String>,
+    #[arg(short, long, help = "otpauth:// uri")]
+    pub uri: Option<String>,
+    #[command(flatten)]
+    pub params: OtpParamOpts,
+    #[arg(short, long, required = true, help = "code to verify")]
+    pub code: String,
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(0..=MAX_WINDOW),
+        help = "number of periods accepted before and after the current one (TOTP), or counters accepted after the given one (HOTP)"
+    )]
+    pub skew: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            v => Err(anyhow::anyhow!("invalid otp algorithm: {}", v)),
+        }
+    }
+}
+
+impl From<OtpAlgorithm> for &'static str {
+    fn from(a: OtpAlgorithm) -> Self {
+        match a {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+impl Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+// 从 --secret, --secret-file 或 --uri 得到 otp 和 hotp 的计数器
+fn load_otp(
+    secret: Option<&str>,
+    secret_file: Option<&str>,
+    uri: Option<&str>,
+    params: &OtpParamOpts,
+) -> Result<(Otp, Option<u64>)> {
+    let secret = match (secret, secret_file) {
+        (Some(secret), _) => Some(secret.to_string()),
+        (None, Some(path)) => Some(String::from_utf8(get_content(path)?)?.trim().to_string()),
+        (None, None) => None,
+    };
+    match (uri, secret) {
+        (Some(uri), _) => Otp::from_uri(uri),
+        (None, Some(secret)) => Ok((
+            Otp::from_base32(&secret)?
+                .algorithm(params.algorithm)
+                .digits(params.digits)?
+                .period(params.period)?,
+            params.counter,
+        )),
+        (None, None) => Err(anyhow::anyhow!(
+            "one of --secret, --secret-file or --uri is required"
+        )),
+    }
+}
+
+fn last_counter(counter: u64, next: u64) -> Result<u64> {
+    counter
+        .checked_add(next)
+        .ok_or_else(|| anyhow::anyhow!("counter {} + {} next codes overflows", counter, next))
+}
+
+fn now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+impl CmdExcutor for OtpGenerateOpts {
+    async fn execute(self) -> Result<()> {
+        let otp = Otp::new(generate_otp_secret(self.bytes as usize))
+            .algorithm(self.algorithm)
+            .digits(self.digits)?
+            .period(self.period)?;
+        println!("{}", otp.secret());
+        println!(
+            "{}",
+            otp.uri(&self.label, self.issuer.as_deref(), self.counter)?
+        );
+        Ok(())
+    }
+}
+
+impl CmdExcutor for OtpCodeOpts {
+    async fn execute(self) -> Result<()> {
+        let (otp, counter) = load_otp(
+            self.secret.as_deref(),
+            self.secret_file.as_deref(),
+            self.uri.as_deref(),
+            &self.params,
+        )?;
+        match counter {
+            Some(counter) => {
+                for c in counter..=last_counter(counter, self.next)? {
+                    println!("{}", otp.hotp(c));
+                }
+            }
+            None => {
+                let now = now()?;
+                let counter = otp.counter(now);
+                for c in counter..=last_counter(counter, self.next)? {
+                    println!("{}", otp.hotp(c));
+                }
+                eprintln!("expires in {}s", otp.remaining(now));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CmdExcutor for OtpVerifyOpts {
+    async fn execute(self) -> Result<()> {
+        let (otp, counter) = load_otp(
+            self.secret.as_deref(),
+            self.secret_file.as_deref(),
+            self.uri.as_deref(),
+            &self.params,
+        )?;
+        let offset = match counter {
+            Some(counter) => otp.verify_hotp(&self.code, counter, self.skew),
+            None => otp.verify_totp(&self.code, now()?, self.skew),
+        };
+        match offset {
+            Some(offset) => {
+                println!("✓ otp code verified, offset {}", offset);
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("⚠ otp code not verified")),
+        }
+    }
+}
+
+impl CmdExcutor for OtpOpts {
+    async fn execute(self) -> Result<()> {
+        self.subcmd.execute().await
+    }
+}
@@ -13,3 +13,10 @@ pub use utils::*;
 pub trait CmdExcutor {
     async fn execute(self) -> Result<()>;
 }
+
+// 较大的子命令参数放在 Box 中, 避免枚举的大小由最大的变体决定
+impl<T: CmdExcutor> CmdExcutor for Box<T> {
+    async fn execute(self) -> Result<()> {
+        (*self).execute().await
+    }
+}
@@ -7,12 +7,51 @@ use rcli::{CmdExcutor, Opts};
 /// - rcli csv
 ///     - ```rcli csv --input in.csv --output out.json --format json```
 ///     - ```rcli csv --input in.csv --output out.yaml --format yaml```
+///     - ```rcli csv --input in.csv --output out.ndjson --format ndjson```
+///     - ```rcli csv --input in.csv --format toml/xml/markdown/table```
+///     - ```rcli csv --input book.xlsx --sheet Players --output out.json```
+///     - ```rcli csv --input data.parquet --format ndjson --output -```
+///     - ```cat in.csv | rcli csv --input - --output - --format ndjson```
 ///     - ```rcli csv --header --delimiter , --input in.csv --output out.yaml --format yaml```
+///     - ```rcli csv --infer --schema schema.yaml --input in.csv --output out.json```
+///     - ```rcli csv --header false --delimiter '\t' --comment '#' --flexible --input in.tsv```
+///     - ```rcli csv --encoding gbk --input export.csv --output out.json```
+///     - ```rcli csv --nested --input fixtures.csv --output fixtures.json```
+///     - ```rcli csv query -i in.csv -s Name,Age --where 'Age > 30 && Nationality == "Italy"' --sort Age:desc --limit 10```
+///     - ```rcli csv stats -i in.csv --top 3 --format markdown```
+///     - ```rcli csv stats -i in.csv --group-by Position --agg count,avg:Age```
+///     - ```rcli csv validate -i in.csv --schema schema.yaml --max-errors 100```
+///     - ```rcli csv join --left players.csv --right clubs.csv --on Name --how left```
+///     - ```rcli csv diff --old v1.csv --new v2.csv --key Name --format yaml```
+///     - ```rcli csv split -i in.csv --rows 10000 --out-dir parts```
+///     - ```rcli csv split -i in.csv --bytes 10M --prefix upload```
+///     - ```rcli csv split -i in.csv --by Position```
+///     - ```rcli csv merge parts/*.csv --union --output merged.csv```
+///     - ```rcli csv mask -i users.csv --columns email,phone:preserve,name:fake --key keyfile```
+///     - ```rcli csv reverse --format json/yaml/ndjson --input in.json --output out.csv```
 /// - rcli genpass
 ///     - ```rcli genpass -l 32 --no-lower --no-lower --no-symbol --no-number```
+///     - ```rcli genpass --words 6 --separator ' ' --capitalize --append-digit```
+///     - ```rcli genpass --count 100 --format csv --output accounts.csv```
+///     - ```rcli genpass -l 12 --min-score 4```
+///     - ```rcli genpass --symbols '!@#$' --min-digits 3 --exclude-ambiguous```
+///     - ```rcli genpass --pattern 'Cvcc-9999-CVC'```
+///     - ```rcli genpass --pronounceable -l 10```
+///     - ```rcli genpass -l 512```
+///     - ```rcli genpass --bytes 32 --encoding base64```
+///     - ```cat passwords.txt | rcli genpass check```
+/// - rcli otp
+///     - ```rcli otp generate --label alice@example.com --issuer ACME```
+///     - ```rcli otp code --secret JBSWY3DPEHPK3PXP --next 1```
+///     - ```rcli otp verify --secret-file secret.txt --code 123456```
+///     - ```rcli otp code --uri 'otpauth://hotp/alice?secret=JBSWY3DPEHPK3PXP&counter=3'```
+///     - ```rcli otp verify --secret JBSWY3DPEHPK3PXP --algorithm sha256 --digits 8 --code 12345678 --skew 2```
 /// - rcli base64
 ///     - ```rcli base64 encode --format nopadding/standard/urlsafe --input textfile```
 ///     - ```rcli base64 decode --format nopadding/standard/urlsafe --input textfile```
+///     - ```rcli base64 decode --input image.b64 --output image.png```
+///     - ```rcli base64 encode --wrap 76 --input image.png --output image.b64```
+///     - ```rcli base64 decode --format auto --input token.txt```
 /// - rcli text
 ///     - ```rcli text sign --format blake3 --key keyfile --input textfile```
 ///     - ```rcli text verify --format blake3 --key keyfile --input textfile --sig signature```
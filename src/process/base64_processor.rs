@@ -1,24 +1,217 @@
-use crate::{cli::Base64Format, utils::get_content};
-use anyhow::Result;
-use base64::prelude::*;
-
-// base64 encoder,
-pub fn process_encode(input: &str, format: Base64Format) -> Result<String> {
-    let data = get_content(input)?;
-    let encoded = match format {
-        Base64Format::Standard => BASE64_STANDARD.encode(&data),
-        Base64Format::UrlSafe => BASE64_URL_SAFE.encode(&data),
-        Base64Format::NoPadding => BASE64_URL_SAFE_NO_PAD.encode(&data),
-    };
-    Ok(encoded)
-}
-
-pub fn process_decode(input: &str, format: Base64Format) -> Result<String> {
-    let data = get_content(input)?;
-    let decode = match format {
-        Base64Format::Standard => BASE64_STANDARD.decode(data)?,
-        Base64Format::UrlSafe => BASE64_URL_SAFE.decode(data)?,
-        Base64Format::NoPadding => BASE64_URL_SAFE_NO_PAD.decode(data)?,
-    };
-    Ok(String::from_utf8(decode)?)
+use crate::cli::Base64Format;
+use anyhow::{anyhow, Result};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    prelude::*,
+    read::DecoderReader,
+    write::EncoderWriter,
+};
+use std::io::{self, Read, Write};
+
+// auto 格式: 由 Base64Reader 统一转换为标准字母表, 有无 padding 都可以解码; 编码时与 standard 相同
+const AUTO: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+fn engine(format: Base64Format) -> &'static GeneralPurpose {
+    match format {
+        Base64Format::Standard => &BASE64_STANDARD,
+        Base64Format::UrlSafe => &BASE64_URL_SAFE,
+        Base64Format::NoPadding => &BASE64_URL_SAFE_NO_PAD,
+        Base64Format::Auto => &AUTO,
+    }
+}
+
+// 解码前跳过空白字符 (PEM, MIME 中的换行和末尾空格)
+// auto 格式下根据第一个 `+` `/` 或 `-` `_` 确定字母表, 之后出现另一种字母表的字符时报错, url safe 的字符转换为 `+` `/`
+struct Base64Reader<R> {
+    inner: R,
+    auto: bool,
+    url_safe: Option<bool>,
+}
+
+impl<R> Base64Reader<R> {
+    fn new(inner: R, auto: bool) -> Self {
+        Self {
+            inner,
+            auto,
+            url_safe: None,
+        }
+    }
+
+    fn detect(&mut self, url_safe: bool) -> io::Result<()> {
+        match self.url_safe {
+            Some(v) if v != url_safe => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "input mixes standard and url safe base64 alphabets",
+            )),
+            _ => {
+                self.url_safe = Some(url_safe);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for Base64Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            let mut len = 0;
+            for i in 0..n {
+                let c = match buf[i] {
+                    c if c.is_ascii_whitespace() => continue,
+                    c @ (b'+' | b'/') if self.auto => {
+                        self.detect(false)?;
+                        c
+                    }
+                    b'-' if self.auto => {
+                        self.detect(true)?;
+                        b'+'
+                    }
+                    b'_' if self.auto => {
+                        self.detect(true)?;
+                        b'/'
+                    }
+                    c => c,
+                };
+                buf[len] = c;
+                len += 1;
+            }
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+// 编码时每 width 个字符换行, 最后一行不追加换行
+struct LineWrapper<W> {
+    inner: W,
+    width: usize,
+    column: usize,
+}
+
+impl<W: Write> Write for LineWrapper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.column == self.width {
+                self.inner.write_all(b"\n")?;
+                self.column = 0;
+            }
+            let n = (self.width - self.column).min(rest.len());
+            self.inner.write_all(&rest[..n])?;
+            self.column += n;
+            rest = &rest[n..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// base64 encoder, 流式编码, 内存占用与输入大小无关, wrap 指定每行的字符数
+pub fn process_encode(
+    reader: impl Read,
+    writer: impl Write,
+    format: Base64Format,
+    wrap: Option<usize>,
+) -> Result<()> {
+    match wrap {
+        Some(0) => Err(anyhow!("wrap width must be greater than 0")),
+        Some(width) => encode(
+            reader,
+            LineWrapper {
+                inner: writer,
+                width,
+                column: 0,
+            },
+            format,
+        ),
+        None => encode(reader, writer, format),
+    }
+}
+
+fn encode(mut reader: impl Read, writer: impl Write, format: Base64Format) -> Result<()> {
+    let mut encoder = EncoderWriter::new(writer, engine(format));
+    io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+// base64 decoder, 流式解码, 输出原始字节, 可以是图片, 压缩包等二进制内容
+pub fn process_decode(
+    reader: impl Read,
+    mut writer: impl Write,
+    format: Base64Format,
+) -> Result<()> {
+    let reader = Base64Reader::new(reader, matches!(format, Base64Format::Auto));
+    let mut decoder = DecoderReader::new(reader, engine(format));
+    io::copy(&mut decoder, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_roundtrip() -> Result<()> {
+        let data = (0..=255u8).cycle().take(100_000).collect::<Vec<_>>();
+        for format in [
+            Base64Format::Standard,
+            Base64Format::UrlSafe,
+            Base64Format::NoPadding,
+        ] {
+            let mut encoded = Vec::new();
+            process_encode(&data[..], &mut encoded, format, None)?;
+            let mut decoded = Vec::new();
+            process_decode(&encoded[..], &mut decoded, format)?;
+            assert_eq!(decoded, data);
+        }
+        let mut decoded = Vec::new();
+        assert!(process_decode(&b"!!!!"[..], &mut decoded, Base64Format::Standard).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrap_and_whitespace() -> Result<()> {
+        let data = [0xfbu8; 100];
+        let mut encoded = Vec::new();
+        process_encode(&data[..], &mut encoded, Base64Format::Standard, Some(76))?;
+        let encoded = String::from_utf8(encoded)?;
+        let lines = encoded.lines().map(str::len).collect::<Vec<_>>();
+        assert_eq!(lines, [76, 60]);
+        assert!(!encoded.ends_with('\n'));
+
+        let pem = format!("{}  \r\n", encoded.replace('\n', "\r\n"));
+        let mut decoded = Vec::new();
+        process_decode(pem.as_bytes(), &mut decoded, Base64Format::Standard)?;
+        assert_eq!(decoded, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_format() -> Result<()> {
+        // `+/8=` 与 `-_8` 都是 [0xfb, 0xff]
+        for input in ["+/8=", "+/8", "-_8=", "-_8\n"] {
+            let mut decoded = Vec::new();
+            process_decode(input.as_bytes(), &mut decoded, Base64Format::Auto)?;
+            assert_eq!(decoded, [0xfb, 0xff], "{}", input);
+        }
+        for input in ["+/-_", "-_8+"] {
+            let mut decoded = Vec::new();
+            assert!(process_decode(input.as_bytes(), &mut decoded, Base64Format::Auto).is_err());
+        }
+        Ok(())
+    }
 }
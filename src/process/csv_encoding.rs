@@ -0,0 +1,157 @@
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{Decoder, DecoderResult, Encoding, UTF_8};
+use std::io::{self, Read};
+
+// 自动检测编码时读取的样本大小
+const SAMPLE_SIZE: usize = 64 * 1024;
+const BUF_SIZE: usize = 8 * 1024;
+
+// 将任意编码的输入转换为 utf-8 供 csv 解析, 去掉开头的 BOM
+// 遇到无法解码的字节时返回 InvalidData 错误, 错误信息中带有所在的行号
+pub struct DecodeReader<R> {
+    inner: R,
+    encoding: &'static Encoding,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+    line: usize,
+    eof: bool,
+    finished: bool,
+}
+
+impl<R: Read> DecodeReader<R> {
+    // encoding 为 None 时自动检测; 有 BOM 时总是以 BOM 为准
+    pub fn new(mut inner: R, encoding: Option<&'static Encoding>) -> io::Result<Self> {
+        let mut input = Vec::with_capacity(SAMPLE_SIZE);
+        (&mut inner)
+            .take(SAMPLE_SIZE as u64)
+            .read_to_end(&mut input)?;
+        let eof = input.len() < SAMPLE_SIZE;
+        let encoding = match Encoding::for_bom(&input) {
+            Some((encoding, bom)) => {
+                input.drain(..bom);
+                encoding
+            }
+            None => encoding.unwrap_or_else(|| detect(&input, eof)),
+        };
+        Ok(Self {
+            inner,
+            encoding,
+            decoder: encoding.new_decoder_without_bom_handling(),
+            input,
+            output: Vec::new(),
+            pos: 0,
+            line: 1,
+            eof,
+            finished: false,
+        })
+    }
+
+    // 读入下一块数据并解码到 output
+    fn fill(&mut self) -> io::Result<()> {
+        if self.input.is_empty() && !self.eof {
+            self.input.resize(BUF_SIZE, 0);
+            let n = self.inner.read(&mut self.input)?;
+            self.input.truncate(n);
+            self.eof = n == 0;
+        }
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length_without_replacement(self.input.len())
+            .unwrap_or(self.input.len() * 3 + 16);
+        self.output.resize(capacity, 0);
+        self.pos = 0;
+        let (result, read, written) = self.decoder.decode_to_utf8_without_replacement(
+            &self.input,
+            &mut self.output,
+            self.eof,
+        );
+        self.output.truncate(written);
+        self.input.drain(..read);
+        let lines = self.output.iter().filter(|&&b| b == b'\n').count();
+        if let DecoderResult::Malformed(..) = result {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid {} byte sequence at line {}",
+                    self.encoding.name(),
+                    self.line + lines
+                ),
+            ));
+        }
+        self.line += lines;
+        self.finished = self.eof && self.input.is_empty();
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// 样本是合法的 utf-8 (末尾可能截断了一个字符) 时使用 utf-8, 否则交给 chardetng 猜测, 例如 GBK 或 windows-1252 (Latin-1)
+fn detect(sample: &[u8], last: bool) -> &'static Encoding {
+    match std::str::from_utf8(sample) {
+        Ok(_) => UTF_8,
+        Err(e) if e.error_len().is_none() && !last => UTF_8,
+        Err(_) => {
+            let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+            detector.feed(sample, last);
+            detector.guess(None, Utf8Detection::Deny)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], encoding: Option<&'static Encoding>) -> io::Result<String> {
+        let mut output = String::new();
+        DecodeReader::new(bytes, encoding)?.read_to_string(&mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_decode_bom() -> io::Result<()> {
+        assert_eq!(
+            decode(b"\xEF\xBB\xBFname\nBuffon\n", None)?,
+            "name\nBuffon\n"
+        );
+        let utf16 = "\u{FEFF}name\nZoë\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(decode(&utf16, None)?, "name\nZoë\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_legacy_encodings() -> io::Result<()> {
+        let (gbk, _, _) = encoding_rs::GBK.encode("姓名,城市\n张三,北京\n李四,上海\n");
+        assert_eq!(decode(&gbk, None)?, "姓名,城市\n张三,北京\n李四,上海\n");
+        let latin1 = b"name,city\nM\xfcller,K\xf6ln\n";
+        let encoding = Encoding::for_label(b"latin1");
+        assert_eq!(decode(latin1, encoding)?, "name,city\nMüller,Köln\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_bytes_line_number() {
+        let err = decode(b"name\nBuffon\nPer\xffin\n", Some(UTF_8)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "invalid UTF-8 byte sequence at line 3");
+    }
+}
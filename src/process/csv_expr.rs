@@ -0,0 +1,387 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::{cmp::Ordering, iter::Peekable, str::Chars, str::FromStr};
+
+// csv query 的过滤表达式, 例如 `age > 30 && nationality == "Italy"`
+// 列名可以用反引号包裹, 例如 `Kit Number` >= 10, 支持 && || ! 和括号, =~ 为正则匹配
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CompareOp, Operand),
+    Match(Operand, Regex),
+}
+
+#[derive(Debug, Clone)]
+pub enum Operand {
+    Column(String),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Expr {
+    pub fn eval(&self, record: &Map<String, Value>) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(record) && b.eval(record),
+            Expr::Or(a, b) => a.eval(record) || b.eval(record),
+            Expr::Not(e) => !e.eval(record),
+            Expr::Compare(a, op, b) => {
+                let ord = compare_values(a.resolve(record), b.resolve(record));
+                match op {
+                    CompareOp::Eq => ord == Some(Ordering::Equal),
+                    CompareOp::Ne => ord != Some(Ordering::Equal),
+                    CompareOp::Gt => ord == Some(Ordering::Greater),
+                    CompareOp::Ge => matches!(ord, Some(Ordering::Greater | Ordering::Equal)),
+                    CompareOp::Lt => ord == Some(Ordering::Less),
+                    CompareOp::Le => matches!(ord, Some(Ordering::Less | Ordering::Equal)),
+                }
+            }
+            Expr::Match(a, re) => match a.resolve(record) {
+                Value::Null => false,
+                Value::String(s) => re.is_match(s),
+                v => re.is_match(&v.to_string()),
+            },
+        }
+    }
+
+    // 表达式中引用的所有列名, 用于在读取记录前检查列是否存在
+    pub fn columns(&self) -> Vec<&String> {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                let mut columns = a.columns();
+                columns.extend(b.columns());
+                columns
+            }
+            Expr::Not(e) => e.columns(),
+            Expr::Compare(a, _, b) => a.column().into_iter().chain(b.column()).collect(),
+            Expr::Match(a, _) => a.column().into_iter().collect(),
+        }
+    }
+}
+
+impl Operand {
+    fn column(&self) -> Option<&String> {
+        match self {
+            Operand::Column(name) => Some(name),
+            Operand::Literal(_) => None,
+        }
+    }
+
+    fn resolve<'a>(&'a self, record: &'a Map<String, Value>) -> &'a Value {
+        match self {
+            Operand::Column(name) => record.get(name).unwrap_or(&Value::Null),
+            Operand::Literal(v) => v,
+        }
+    }
+}
+
+// 比较两个值: 两边都能解析为数字时按数字比较, 否则按字符串比较, null 只与 null 相等
+pub fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => match (as_number(a), as_number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(as_text(a).cmp(&as_text(b))),
+        },
+    }
+}
+
+// 排序使用的全序: 数字 (包括可以解析为数字的字符串) 排在布尔值之前, 布尔值排在其他值之前
+// compare_values 在数字和字符串混合时不满足传递性, 不能直接用于排序
+pub fn sort_values(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> (u8, Option<f64>) {
+        match (v, as_number(v)) {
+            (_, Some(n)) => (0, Some(n)),
+            (Value::Bool(_), _) => (1, None),
+            _ => (2, None),
+        }
+    }
+    match (rank(a), rank(b)) {
+        ((0, Some(x)), (0, Some(y))) => x.total_cmp(&y),
+        ((1, _), (1, _)) => a.as_bool().cmp(&b.as_bool()),
+        ((2, _), (2, _)) => as_text(a).cmp(&as_text(b)),
+        ((x, _), (y, _)) => x.cmp(&y),
+    }
+}
+
+fn as_number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn as_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+impl FromStr for Expr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(t) => Err(anyhow!("unexpected token {:?} in expression: {}", t, s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '"' | '\'' => tokens.push(Token::Literal(Value::String(read_quoted(&mut chars)?))),
+            '`' => tokens.push(Token::Ident(read_quoted(&mut chars)?)),
+            '=' | '!' | '<' | '>' | '&' | '|' => tokens.push(Token::Op(read_op(&mut chars)?)),
+            c if c.is_ascii_digit() || c == '-' => {
+                let text = read_while(&mut chars, |c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')
+                });
+                let number = text
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .or_else(|_| text.parse::<f64>().map(Value::from))
+                    .map_err(|_| anyhow!("invalid number: {}", text))?;
+                tokens.push(Token::Literal(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let word = read_while(&mut chars, |c| {
+                    c.is_alphanumeric() || matches!(c, '_' | '.' | '[' | ']')
+                });
+                tokens.push(match word.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    "null" => Token::Literal(Value::Null),
+                    _ => Token::Ident(word),
+                });
+            }
+            c => return Err(anyhow!("unexpected character {:?} in expression", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn read_while(chars: &mut Peekable<Chars>, f: impl Fn(char) -> bool) -> String {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        if !f(c) {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    s
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>) -> Result<String> {
+    let quote = chars.next().unwrap_or_default();
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            // 只处理引号和反斜杠的转义, 其它反斜杠原样保留, 便于书写正则
+            Some('\\') => match chars.next() {
+                Some(c) if c == quote || c == '\\' => s.push(c),
+                Some(c) => {
+                    s.push('\\');
+                    s.push(c);
+                }
+                None => return Err(anyhow!("unterminated {} in expression", quote)),
+            },
+            Some(c) if c == quote => return Ok(s),
+            Some(c) => s.push(c),
+            None => return Err(anyhow!("unterminated {} in expression", quote)),
+        }
+    }
+}
+
+fn read_op(chars: &mut Peekable<Chars>) -> Result<&'static str> {
+    let first = chars.next().unwrap_or_default();
+    let second = chars.peek().copied();
+    let op = match (first, second) {
+        ('=', Some('=')) => "==",
+        ('=', Some('~')) => "=~",
+        ('!', Some('=')) => "!=",
+        ('>', Some('=')) => ">=",
+        ('<', Some('=')) => "<=",
+        ('&', Some('&')) => "&&",
+        ('|', Some('|')) => "||",
+        ('!', _) => return Ok("!"),
+        ('>', _) => return Ok(">"),
+        ('<', _) => return Ok("<"),
+        ('=', _) => return Ok("=="),
+        (c, _) => return Err(anyhow!("unexpected operator {:?} in expression", c)),
+    };
+    chars.next();
+    Ok(op)
+}
+
+// 递归下降解析: or := and ("||" and)*, and := unary ("&&" unary)*, unary := "!" unary | primary
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Op("||")) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::Op("&&")) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Op("!")) => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::LParen) => {
+                self.next();
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(anyhow!("missing ')' in expression")),
+                }
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr> {
+        let left = self.parse_operand()?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            t => return Err(anyhow!("expect a comparison operator, got: {:?}", t)),
+        };
+        let right = self.parse_operand()?;
+        let op = match op {
+            "==" => CompareOp::Eq,
+            "!=" => CompareOp::Ne,
+            ">" => CompareOp::Gt,
+            ">=" => CompareOp::Ge,
+            "<" => CompareOp::Lt,
+            "<=" => CompareOp::Le,
+            "=~" => {
+                return match right {
+                    Operand::Literal(Value::String(pattern)) => {
+                        Ok(Expr::Match(left, Regex::new(&pattern)?))
+                    }
+                    _ => Err(anyhow!("=~ expects a string pattern")),
+                }
+            }
+            op => return Err(anyhow!("unexpected operator {:?}", op)),
+        };
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(Operand::Column(name)),
+            Some(Token::Literal(v)) => Ok(Operand::Literal(v)),
+            t => Err(anyhow!("expect a column or a literal, got: {:?}", t)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(v: Value) -> Map<String, Value> {
+        match v {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_eval_expr() -> Result<()> {
+        let row =
+            record(json!({"age": "31", "nationality": "Italy", "Kit Number": 77, "note": null}));
+        let cases = [
+            (r#"age > 30 && nationality == "Italy""#, true),
+            (r#"age > 30 && nationality != 'Italy'"#, false),
+            ("`Kit Number` >= 77 || age < 18", true),
+            ("!(age <= 31)", false),
+            ("note == null && missing == null", true),
+            (r#"nationality =~ "^It""#, true),
+            (r#"age =~ "^\d+$""#, true),
+            ("age == 31.0", true),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(expr.parse::<Expr>()?.eval(&row), expected, "{}", expr);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_expr() {
+        assert!("age >".parse::<Expr>().is_err());
+        assert!("(age > 1".parse::<Expr>().is_err());
+        assert!("age > 1 age".parse::<Expr>().is_err());
+        assert!("name == \"abc".parse::<Expr>().is_err());
+    }
+}
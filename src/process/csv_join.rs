@@ -0,0 +1,304 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, io::Write};
+
+use crate::cli::{JoinKind, OutputFormat};
+use crate::process::csv_processor::CsvReaderOptions;
+use crate::process::record_writer::record_writer;
+
+type Record = Map<String, Value>;
+
+fn key_of(record: &Record, on: &[String]) -> Vec<String> {
+    on.iter()
+        .map(|c| {
+            record
+                .get(c)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect()
+}
+
+// stdin 只能读取一次, 两个输入不能同时为 `-`
+fn check_inputs(a: &str, b: &str) -> Result<()> {
+    if a == "-" && b == "-" {
+        return Err(anyhow!("only one of the two inputs can be read from stdin"));
+    }
+    Ok(())
+}
+
+fn check_columns(headers: &[String], on: &[String], file: &str) -> Result<()> {
+    match on.iter().find(|c| !headers.contains(c)) {
+        Some(c) => Err(anyhow!("key column {} not found in {}", c, file)),
+        None => Ok(()),
+    }
+}
+
+// 右表中与左表重名的列加上 `_right` 后缀, 加上后缀仍然重名时继续追加, 直到列名不再重复
+fn right_name(column: &str, used: &[String]) -> String {
+    let mut name = column.to_string();
+    while used.contains(&name) {
+        name.push_str("_right");
+    }
+    name
+}
+
+// 和 sql 的 null 一样, key 列为空的记录不与任何记录匹配
+fn join_key(record: &Record, on: &[String]) -> Option<Vec<String>> {
+    let key = key_of(record, on);
+    (!key.iter().any(String::is_empty)).then_some(key)
+}
+
+// 按 key 列连接两个 csv 文件, 右表全部读入内存, 左表逐条处理
+// 右表中与左表重名的非 key 列加上 `_right` 后缀, key 列为空的记录视为没有匹配
+pub fn process_join(
+    left: &str,
+    right: &str,
+    writer: impl Write,
+    format: OutputFormat,
+    options: CsvReaderOptions,
+    on: &[String],
+    how: JoinKind,
+) -> Result<()> {
+    check_inputs(left, right)?;
+    let mut left_source = options.open(left)?;
+    let mut right_source = options.open(right)?;
+    check_columns(left_source.headers(), on, left)?;
+    check_columns(right_source.headers(), on, right)?;
+
+    let left_columns = left_source.headers().to_vec();
+    let mut used = left_columns.clone();
+    let mut right_columns = Vec::new();
+    for c in right_source.headers().iter().filter(|c| !on.contains(c)) {
+        let name = right_name(c, &used);
+        used.push(name.clone());
+        right_columns.push((c.clone(), name));
+    }
+
+    let mut right_rows: Vec<(Record, bool)> = Vec::new();
+    let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for record in right_source.records() {
+        let record = record?;
+        if let Some(key) = join_key(&record, on) {
+            index.entry(key).or_default().push(right_rows.len());
+        }
+        right_rows.push((record, false));
+    }
+
+    let merge = |left: Option<&Record>, right: Option<&Record>| -> Value {
+        let mut row = Record::new();
+        for c in &left_columns {
+            let value = match (left, right) {
+                (Some(l), _) => l.get(c).cloned(),
+                (None, Some(r)) if on.contains(c) => r.get(c).cloned(),
+                _ => None,
+            };
+            row.insert(c.clone(), value.unwrap_or(Value::Null));
+        }
+        for (c, name) in &right_columns {
+            let value = right.and_then(|r| r.get(c).cloned());
+            row.insert(name.clone(), value.unwrap_or(Value::Null));
+        }
+        Value::Object(row)
+    };
+
+    let mut writer = record_writer(format, Box::new(writer));
+    for record in left_source.records() {
+        let record = record?;
+        match join_key(&record, on).and_then(|key| index.get(&key)) {
+            Some(matched) => {
+                for &i in matched {
+                    right_rows[i].1 = true;
+                    writer.write(&merge(Some(&record), Some(&right_rows[i].0)))?;
+                }
+            }
+            None if how != JoinKind::Inner => writer.write(&merge(Some(&record), None))?,
+            None => {}
+        }
+    }
+    if how == JoinKind::Outer {
+        for (record, _) in right_rows.iter().filter(|(_, matched)| !matched) {
+            writer.write(&merge(None, Some(record)))?;
+        }
+    }
+    writer.finish()
+}
+
+// 比较两个 csv 文件, 输出新增 (added), 删除 (removed) 和修改 (changed) 的记录, 修改的记录列出每个单元格的新旧值
+// 旧文件全部读入内存, 新文件逐条处理; 新增和修改按新文件的顺序输出, 删除的记录最后按旧文件的顺序输出
+pub fn process_diff(
+    old: &str,
+    new: &str,
+    writer: impl Write,
+    format: OutputFormat,
+    options: CsvReaderOptions,
+    key: &[String],
+) -> Result<()> {
+    check_inputs(old, new)?;
+    let mut old_source = options.open(old)?;
+    let mut new_source = options.open(new)?;
+    check_columns(old_source.headers(), key, old)?;
+    check_columns(new_source.headers(), key, new)?;
+
+    let mut old_rows: Vec<Option<Record>> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    for record in old_source.records() {
+        let record = record?;
+        let k = key_of(&record, key);
+        if index.insert(k.clone(), old_rows.len()).is_some() {
+            return Err(anyhow!("duplicate key {:?} in {}", k, old));
+        }
+        old_rows.push(Some(record));
+    }
+
+    let key_value = |k: Vec<String>| -> Value {
+        Value::Object(
+            key.iter()
+                .cloned()
+                .zip(k.into_iter().map(Value::String))
+                .collect(),
+        )
+    };
+
+    let mut writer = record_writer(format, Box::new(writer));
+    let mut seen = HashMap::new();
+    for record in new_source.records() {
+        let record = record?;
+        let k = key_of(&record, key);
+        if seen.insert(k.clone(), ()).is_some() {
+            return Err(anyhow!("duplicate key {:?} in {}", k, new));
+        }
+        match index.get(&k).and_then(|&i| old_rows[i].take()) {
+            Some(old_record) => {
+                let changes = diff_cells(&old_record, &record);
+                if !changes.is_empty() {
+                    writer.write(&json!({
+                        "status": "changed",
+                        "key": key_value(k),
+                        "changes": changes,
+                    }))?;
+                }
+            }
+            None => writer.write(&json!({
+                "status": "added",
+                "key": key_value(k),
+                "record": record,
+            }))?,
+        }
+    }
+    for record in old_rows.into_iter().flatten() {
+        writer.write(&json!({
+            "status": "removed",
+            "key": key_value(key_of(&record, key)),
+            "record": record,
+        }))?;
+    }
+    writer.finish()
+}
+
+// 对比两条记录的所有列, 某一侧缺少的列视为 null
+fn diff_cells(old: &Record, new: &Record) -> Record {
+    let mut changes = Record::new();
+    let columns = old
+        .keys()
+        .chain(new.keys().filter(|k| !old.contains_key(*k)));
+    for c in columns {
+        let before = old.get(c).unwrap_or(&Value::Null);
+        let after = new.get(c).unwrap_or(&Value::Null);
+        if before != after {
+            changes.insert(c.clone(), json!({"old": before, "new": after}));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_files(dir: &TempDir, a: &str, b: &str) -> Result<(String, String)> {
+        let (pa, pb) = (dir.path().join("a.csv"), dir.path().join("b.csv"));
+        fs::write(&pa, a)?;
+        fs::write(&pb, b)?;
+        Ok((
+            pa.to_string_lossy().to_string(),
+            pb.to_string_lossy().to_string(),
+        ))
+    }
+
+    #[test]
+    fn test_join() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (a, b) = write_files(
+            &dir,
+            "id,name\n1,Buffon\n2,Perin\n",
+            "id,name,club\n1,Gigi,Juventus\n3,Chiellini,LAFC\n",
+        )?;
+        let run = |how| -> Result<String> {
+            let mut output = Vec::new();
+            let on = ["id".to_string()];
+            let options = CsvReaderOptions::default();
+            process_join(&a, &b, &mut output, OutputFormat::Ndjson, options, &on, how)?;
+            Ok(String::from_utf8(output)?)
+        };
+        assert_eq!(
+            run(JoinKind::Inner)?,
+            "{\"id\":\"1\",\"name\":\"Buffon\",\"name_right\":\"Gigi\",\"club\":\"Juventus\"}\n"
+        );
+        assert_eq!(run(JoinKind::Left)?.lines().count(), 2);
+        let outer = run(JoinKind::Outer)?;
+        assert_eq!(
+            outer.lines().last(),
+            Some("{\"id\":\"3\",\"name\":null,\"name_right\":\"Chiellini\",\"club\":\"LAFC\"}")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_name_clash_and_empty_key() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (a, b) = write_files(
+            &dir,
+            "id,name,name_right\n1,Buffon,Gigi\n,Perin,Mattia\n",
+            "id,name,name_right\n1,Gianluigi,GG\n,Pinsoglio,Carlo\n",
+        )?;
+        let mut output = Vec::new();
+        let on = ["id".to_string()];
+        let options = CsvReaderOptions::default();
+        let format = OutputFormat::Ndjson;
+        process_join(&a, &b, &mut output, format, options, &on, JoinKind::Inner)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "{\"id\":\"1\",\"name\":\"Buffon\",\"name_right\":\"Gigi\",\"name_right_right\":\"Gianluigi\",\"name_right_right_right\":\"GG\"}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (a, b) = write_files(
+            &dir,
+            "id,name,kit\n1,Buffon,77\n2,Perin,37\n3,Pinsoglio,31\n",
+            "id,name,kit\n1,Buffon,1\n3,Pinsoglio,31\n4,Szczesny,1\n",
+        )?;
+        let mut output = Vec::new();
+        let key = ["id".to_string()];
+        let options = CsvReaderOptions::default();
+        process_diff(&a, &b, &mut output, OutputFormat::Ndjson, options, &key)?;
+        let output = String::from_utf8(output)?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"{"status":"changed","key":{"id":"1"},"changes":{"kit":{"old":"77","new":"1"}}}"#,
+                r#"{"status":"added","key":{"id":"4"},"record":{"id":"4","name":"Szczesny","kit":"1"}}"#,
+                r#"{"status":"removed","key":{"id":"2"},"record":{"id":"2","name":"Perin","kit":"37"}}"#,
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{anyhow, Result};
+use csv::StringRecord;
+use rand::{Rng, RngCore};
+use std::io::Write;
+
+use crate::cli::MaskMethod;
+use crate::process::csv_processor::CsvReaderOptions;
+use crate::process::text::Blake3;
+
+const REDACTED: &str = "[REDACTED]";
+
+// CJK 统一表意文字的基本区
+const CJK_START: char = '\u{4e00}';
+const CJK_END: char = '\u{9fff}';
+
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bruno", "Chiara", "Daniel", "Elena", "Felix", "Giulia", "Hugo", "Irene", "Jonas",
+    "Laura", "Marco", "Nadia", "Oscar", "Paola", "Rafael", "Sofia", "Tomas", "Ursula", "Victor",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Bianchi", "Costa", "Dubois", "Esposito", "Fischer", "Garcia", "Hansen", "Jensen", "Keller",
+    "Lambert", "Moreau", "Novak", "Olsen", "Petrov", "Rossi", "Schmidt", "Silva", "Weber",
+];
+
+const CITIES: &[&str] = &[
+    "Turin", "Milan", "Lyon", "Porto", "Hamburg", "Krakow", "Ghent", "Malmo", "Graz", "Bilbao",
+];
+
+// 基于 keyed blake3 的确定性脱敏, 相同的 key 和值在任何列, 任何一次运行中都得到相同的结果
+pub struct Masker {
+    blake3: Blake3,
+}
+
+// 从 keyed hash 的输出中依次取随机数
+struct HashStream(blake3::OutputReader);
+
+impl RngCore for HashStream {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0u8; 4];
+        self.0.fill(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0u8; 8];
+        self.0.fill(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl HashStream {
+    // gen_range 没有取模偏差
+    fn pick(&mut self, n: usize) -> usize {
+        self.gen_range(0..n)
+    }
+
+    fn choose<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.pick(items.len())]
+    }
+}
+
+impl Masker {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            blake3: Blake3::new(key),
+        }
+    }
+
+    // 从 key 文件的内容创建, key 必须是 32 字节
+    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
+        Ok(Self {
+            blake3: Blake3::try_new(key)?,
+        })
+    }
+
+    // 空单元格保持为空
+    pub fn mask(&self, column: &str, value: &str, method: MaskMethod) -> String {
+        if value.is_empty() {
+            return String::new();
+        }
+        match method {
+            MaskMethod::Hash => self.blake3.hash(value.as_bytes()).to_hex().to_string(),
+            MaskMethod::Redact => REDACTED.to_string(),
+            MaskMethod::Preserve => self.preserve(value),
+            MaskMethod::Fake => self.fake(column, value),
+        }
+    }
+
+    fn stream(&self, value: &str) -> HashStream {
+        HashStream(self.blake3.hash_xof(value.as_bytes()))
+    }
+
+    // 保留格式: 数字替换为数字, 字母替换为相同大小写的字母, 汉字替换为汉字, 标点和空白不变
+    // 非 ASCII 的字母 (如 `é`, `ß`) 替换为 ASCII 字母, 保证原值中的字母和数字不会出现在结果中
+    fn preserve(&self, value: &str) -> String {
+        let mut stream = self.stream(value);
+        value
+            .chars()
+            .map(|c| match c {
+                c if c.is_numeric() => (b'0' + stream.pick(10) as u8) as char,
+                CJK_START..=CJK_END => {
+                    let offset = stream.pick(CJK_END as usize - CJK_START as usize + 1);
+                    char::from_u32(CJK_START as u32 + offset as u32).unwrap_or(CJK_START)
+                }
+                c if c.is_uppercase() => (b'A' + stream.pick(26) as u8) as char,
+                c if c.is_alphabetic() => (b'a' + stream.pick(26) as u8) as char,
+                c => c,
+            })
+            .collect()
+    }
+
+    // 根据值的形式和列名生成看起来真实的值: 邮箱, 姓名, 城市; 其它值 (包括电话号码) 按保留格式处理
+    // 姓名只匹配完整的列名, 避免 username, filename 这类列被替换为人名
+    fn fake(&self, column: &str, value: &str) -> String {
+        let mut stream = self.stream(value);
+        let column = column.to_lowercase().replace([' ', '-'], "_");
+        if value.contains('@') || column.contains("email") {
+            let first = stream.choose(FIRST_NAMES).to_lowercase();
+            let last = stream.choose(LAST_NAMES).to_lowercase();
+            let domain = stream.choose(&["example.com", "example.org", "example.net"]);
+            format!("{}.{}{}@{}", first, last, stream.pick(100), domain)
+        } else if column == "first_name" {
+            stream.choose(FIRST_NAMES).to_string()
+        } else if column == "last_name" || column == "surname" {
+            stream.choose(LAST_NAMES).to_string()
+        } else if column == "name" || column == "full_name" {
+            let first = stream.choose(FIRST_NAMES);
+            format!("{} {}", first, stream.choose(LAST_NAMES))
+        } else if column.contains("city") {
+            stream.choose(CITIES).to_string()
+        } else {
+            self.preserve(value)
+        }
+    }
+}
+
+// 对指定的列脱敏后输出 csv, 其它列原样输出
+pub fn process(
+    input: &str,
+    writer: impl Write,
+    options: CsvReaderOptions,
+    columns: &[(String, MaskMethod)],
+    masker: Masker,
+) -> Result<()> {
+    let mut source = options.open(input)?;
+    let rules = columns
+        .iter()
+        .map(|(column, method)| {
+            source
+                .headers()
+                .iter()
+                .position(|h| h == column)
+                .map(|i| (i, column.as_str(), *method))
+                .ok_or_else(|| anyhow!("unknown column: {}", column))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut writer = options.writer_builder().flexible(true).from_writer(writer);
+    if options.has_headers {
+        writer.write_record(source.headers())?;
+    }
+    for record in source.string_records() {
+        let record = record?;
+        let mut fields = record.iter().map(String::from).collect::<Vec<_>>();
+        for (i, column, method) in &rules {
+            if let Some(field) = fields.get_mut(*i) {
+                *field = masker.mask(column, field, *method);
+            }
+        }
+        writer.write_record(&StringRecord::from(fields))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn test_mask_deterministic() {
+        let masker = Masker::new(KEY);
+        let hash = masker.mask("email", "gigi@juventus.com", MaskMethod::Hash);
+        assert_eq!(
+            hash,
+            blake3::keyed_hash(&KEY, b"gigi@juventus.com")
+                .to_hex()
+                .as_str()
+        );
+        for method in [MaskMethod::Preserve, MaskMethod::Fake] {
+            let a = masker.mask("email", "gigi@juventus.com", method);
+            assert_eq!(
+                a,
+                Masker::new(KEY).mask("email", "gigi@juventus.com", method)
+            );
+            assert_ne!(
+                a,
+                Masker::new([8; 32]).mask("email", "gigi@juventus.com", method)
+            );
+        }
+        assert_eq!(masker.mask("email", "", MaskMethod::Redact), "");
+    }
+
+    #[test]
+    fn test_mask_formats() {
+        let masker = Masker::new(KEY);
+        let phone = masker.mask("phone", "+39 011-555-0177", MaskMethod::Preserve);
+        assert_eq!(phone.len(), 16);
+        assert!(phone.starts_with('+'));
+        assert_eq!(&phone[3..4], " ");
+        assert_eq!(phone.chars().filter(|c| c.is_ascii_digit()).count(), 12);
+
+        let email = masker.mask("contact", "gigi@juventus.com", MaskMethod::Fake);
+        assert!(email.contains("@example."));
+        let name = masker.mask("Full Name", "Gianluigi Buffon", MaskMethod::Fake);
+        assert_eq!(name.split(' ').count(), 2);
+        let username = masker.mask("username", "gigi_1978", MaskMethod::Fake);
+        assert_eq!(username.len(), 9);
+        assert_eq!(&username[4..5], "_");
+    }
+
+    #[test]
+    fn test_mask_non_ascii() {
+        let masker = Masker::new(KEY);
+        for method in [MaskMethod::Preserve, MaskMethod::Fake] {
+            let masked = masker.mask("address", "Zoë Müller, 北京市朝阳区 ١٢٣", method);
+            assert_eq!(masked.chars().count(), 22);
+            for c in "ëüÜ北京市朝阳区١٢٣".chars() {
+                assert!(!masked.contains(c), "{} leaked in {}", c, masked);
+            }
+            let chars = masked.chars().collect::<Vec<_>>();
+            assert_eq!(chars[3], ' ');
+            assert!(chars[12..18]
+                .iter()
+                .all(|c| (CJK_START..=CJK_END).contains(c)));
+            assert!(chars[19..].iter().all(|c| c.is_ascii_digit()));
+        }
+    }
+
+    #[test]
+    fn test_process_mask() -> Result<()> {
+        let mut output = Vec::new();
+        let columns = [
+            ("Name".to_string(), MaskMethod::Redact),
+            ("Kit Number".to_string(), MaskMethod::Preserve),
+        ];
+        process(
+            "assets/juventus.csv",
+            &mut output,
+            CsvReaderOptions::default(),
+            &columns,
+            Masker::new(KEY),
+        )?;
+        let output = String::from_utf8(output)?;
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Name,Position,DOB,Nationality,Kit Number")
+        );
+        let first = lines.next().unwrap_or_default();
+        assert!(first.starts_with("[REDACTED],Goalkeeper,\"Apr 18, 1990 (29)\",Poland,"));
+        assert_eq!(first.rsplit(',').next().map(str::len), Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+// 数组下标的上限, 避免 `tags[999999999]` 这样的列名分配大量的 null
+const MAX_INDEX: usize = 10_000;
+
+// 列名中的一段路径, `address.city` 对应两个 Key, `tags[0]` 对应 Key 和 Index
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+// 解析 `a.b[0].c` 形式的列名, 无法解析的列名 (如 `a..b`, `a[x]`) 返回 None, 作为普通的 key 处理
+fn parse_path(column: &str) -> Option<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    for part in column.split('.') {
+        let (key, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if key.is_empty() {
+            return None;
+        }
+        segments.push(Segment::Key(key));
+        while !rest.is_empty() {
+            let end = rest.find(']')?;
+            let index = rest.strip_prefix('[')?[..end - 1].parse().ok()?;
+            segments.push(Segment::Index(index));
+            rest = &rest[end + 1..];
+        }
+    }
+    Some(segments)
+}
+
+// 根据 `a.b` / `a[0]` 形式的列名构建嵌套的对象和数组
+pub fn unflatten(record: Map<String, Value>) -> Result<Value> {
+    let mut root = Value::Object(Map::new());
+    for (column, value) in record {
+        match parse_path(&column) {
+            Some(path) => insert(&mut root, &path, value)
+                .map_err(|e| anyhow!("column {:?}: {}", column, e))?,
+            None => insert(&mut root, &[Segment::Key(&column)], value)?,
+        }
+    }
+    Ok(root)
+}
+
+fn insert(node: &mut Value, path: &[Segment], value: Value) -> Result<()> {
+    let Some((segment, rest)) = path.split_first() else {
+        if !node.is_null() {
+            return Err(anyhow!("conflicts with another column"));
+        }
+        *node = value;
+        return Ok(());
+    };
+    let child = match segment {
+        Segment::Key(key) => {
+            if node.is_null() {
+                *node = Value::Object(Map::new());
+            }
+            let obj = node
+                .as_object_mut()
+                .ok_or_else(|| anyhow!("{:?} is not an object", key))?;
+            obj.entry(key.to_string()).or_insert(Value::Null)
+        }
+        Segment::Index(index) => {
+            if *index > MAX_INDEX {
+                return Err(anyhow!(
+                    "array index {} is out of range, the maximum is {}",
+                    index,
+                    MAX_INDEX
+                ));
+            }
+            if node.is_null() {
+                *node = Value::Array(Vec::new());
+            }
+            let items = node
+                .as_array_mut()
+                .ok_or_else(|| anyhow!("[{}] is not an array item", index))?;
+            if items.len() <= *index {
+                let len = index
+                    .checked_add(1)
+                    .ok_or_else(|| anyhow!("array index {} is out of range", index))?;
+                items.resize(len, Value::Null);
+            }
+            &mut items[*index]
+        }
+    };
+    insert(child, rest, value)
+}
+
+// 将嵌套对象展平为 `a.b` 形式的列名, 数组展平为 `a[0]` 形式的列名, 展平后的值均为字符串
+pub fn flatten(record: &Value) -> Result<Map<String, Value>> {
+    match record {
+        Value::Object(_) => {
+            let mut map = Map::new();
+            flatten_into(&mut map, String::new(), record);
+            Ok(map)
+        }
+        v => Err(anyhow!("expect an object record, got: {}", v)),
+    }
+}
+
+fn flatten_into(map: &mut Map<String, Value>, prefix: String, value: &Value) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (k, v) in obj {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                flatten_into(map, key, v);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, v) in items.iter().enumerate() {
+                flatten_into(map, format!("{}[{}]", prefix, i), v);
+            }
+        }
+        Value::Object(_) | Value::Array(_) | Value::Null => {
+            map.insert(prefix, Value::String(String::new()));
+        }
+        Value::String(s) => {
+            map.insert(prefix, Value::String(s.clone()));
+        }
+        v => {
+            map.insert(prefix, Value::String(v.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("a.b[1][2].c"),
+            Some(vec![
+                Segment::Key("a"),
+                Segment::Key("b"),
+                Segment::Index(1),
+                Segment::Index(2),
+                Segment::Key("c")
+            ])
+        );
+        assert_eq!(parse_path("a..b"), None);
+        assert_eq!(parse_path("a[x]"), None);
+        assert_eq!(parse_path("a[1"), None);
+    }
+
+    #[test]
+    fn test_unflatten() -> Result<()> {
+        let record = json!({
+            "name": "Buffon",
+            "address.city": "Turin",
+            "address.zip": 10151,
+            "tags[0]": "gk",
+            "tags[2]": "captain",
+            "a..b": "kept",
+        });
+        let Value::Object(record) = record else {
+            unreachable!()
+        };
+        let nested = unflatten(record.clone())?;
+        assert_eq!(
+            nested,
+            json!({
+                "name": "Buffon",
+                "address": {"city": "Turin", "zip": 10151},
+                "tags": ["gk", null, "captain"],
+                "a..b": "kept",
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten_conflict() {
+        let record = json!({"a": "1", "a.b": "2"});
+        let Value::Object(record) = record else {
+            unreachable!()
+        };
+        assert!(unflatten(record).is_err());
+    }
+
+    #[test]
+    fn test_unflatten_index_out_of_range() {
+        for column in ["tags[18446744073709551615]", "x[999999999]"] {
+            let mut record = Map::new();
+            record.insert(column.to_string(), json!("1"));
+            let err = unflatten(record).unwrap_err().to_string();
+            assert!(err.contains("out of range"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let value = json!({"a": {"b": "1", "c": ["x", "y"]}, "d": "2"});
+        assert_eq!(unflatten(flatten(&value)?)?, value);
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use bytes::Bytes;
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+use serde_json::Map;
+use std::{fs::File, io::Write};
+
+use crate::cli::OutputFormat;
+use crate::get_content;
+use crate::process::csv_processor::{write_records, ConvertOptions};
+
+// 逐行读取 parquet 文件并按 csv 转换的方式输出, 嵌套的 group/list/map 转换为 json 对象和数组
+// stdin 不支持随机读取, 需要先读入内存
+pub fn process(
+    input: &str,
+    writer: impl Write,
+    output_format: OutputFormat,
+    convert: ConvertOptions,
+) -> Result<()> {
+    if input == "-" {
+        let reader = SerializedFileReader::new(Bytes::from(get_content(input)?))?;
+        write_rows(reader, writer, output_format, convert)
+    } else {
+        let reader = SerializedFileReader::new(File::open(input)?)?;
+        write_rows(reader, writer, output_format, convert)
+    }
+}
+
+fn write_rows<R: ChunkReader + 'static>(
+    reader: SerializedFileReader<R>,
+    writer: impl Write,
+    output_format: OutputFormat,
+    convert: ConvertOptions,
+) -> Result<()> {
+    let records = reader.get_row_iter(None)?.map(|row| {
+        Ok(row?
+            .get_column_iter()
+            .map(|(name, field)| (name.clone(), field.to_json_value()))
+            .collect::<Map<_, _>>())
+    });
+    let types = convert.schema_types()?;
+    write_records(records, writer, output_format, &types, &convert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType, Int32Type},
+        file::writer::SerializedFileWriter,
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_process_parquet() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("players.parquet");
+        let schema = parse_message_type(
+            "message player { required binary name (UTF8); optional int32 kit; }",
+        )?;
+        let mut writer =
+            SerializedFileWriter::new(File::create(&path)?, Arc::new(schema), Default::default())?;
+        let mut group = writer.next_row_group()?;
+        if let Some(mut column) = group.next_column()? {
+            let names = [ByteArray::from("Buffon"), ByteArray::from("Perin")];
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&names, None, None)?;
+            column.close()?;
+        }
+        if let Some(mut column) = group.next_column()? {
+            column
+                .typed::<Int32Type>()
+                .write_batch(&[77], Some(&[1, 0]), None)?;
+            column.close()?;
+        }
+        group.close()?;
+        writer.close()?;
+
+        let mut output = Vec::new();
+        process(
+            &path.to_string_lossy(),
+            &mut output,
+            OutputFormat::Ndjson,
+            ConvertOptions::default(),
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "{\"name\":\"Buffon\",\"kit\":77}\n{\"name\":\"Perin\",\"kit\":null}\n"
+        );
+        Ok(())
+    }
+}
@@ -1,27 +1,334 @@
-use anyhow::Result;
-use std::fs;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::io::{Read, Write};
 
-use csv::Reader;
+use csv::{Reader, ReaderBuilder, StringRecord, WriterBuilder};
+use encoding_rs::Encoding;
 
 use crate::cli::OutputFormat;
+use crate::process::csv_encoding::DecodeReader;
+use crate::process::csv_nested::unflatten;
+use crate::process::csv_types::{infer_types, ColumnTypes};
+use crate::process::record_writer::record_writer;
+use crate::{get_content, get_reader};
 
-// 将 csv 文件转换为 json 或者 yaml 格式
-pub fn process(input: &str, output: String, output_format: OutputFormat) -> Result<()> {
-    let mut rdr = Reader::from_path(input)?;
-    let mut ret = Vec::with_capacity(100);
-    let headers = rdr.headers()?.clone();
-    for result in rdr.records() {
-        let record = result?;
-        let json_value = headers
-            .iter()
-            .zip(record.iter())
-            .collect::<serde_json::Value>();
-        ret.push(json_value)
-    }
-    let contents = match output_format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
-    };
-    fs::write(output, contents)?;
-    Ok(())
+// csv 解析选项, 对应 csv::ReaderBuilder 的配置
+#[derive(Debug, Clone, Copy)]
+pub struct CsvReaderOptions {
+    pub delimiter: u8,
+    pub has_headers: bool,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    pub comment: Option<u8>,
+    pub flexible: bool,
+    // 输入的编码, None 表示自动检测
+    pub encoding: Option<&'static Encoding>,
+}
+
+impl Default for CsvReaderOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+            quote: b'"',
+            escape: None,
+            comment: None,
+            flexible: false,
+            encoding: None,
+        }
+    }
+}
+
+impl CsvReaderOptions {
+    pub fn builder(&self) -> ReaderBuilder {
+        let mut builder = ReaderBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .quote(self.quote)
+            .escape(self.escape)
+            .comment(self.comment)
+            .flexible(self.flexible);
+        builder
+    }
+
+    // 写出 csv 时使用与输入相同的分隔符和引号
+    pub fn writer_builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder.delimiter(self.delimiter).quote(self.quote);
+        builder
+    }
+
+    // 打开输入文件, `-` 表示 stdin
+    pub fn open(&self, input: &str) -> Result<CsvSource<DecodeReader<Box<dyn Read>>>> {
+        let rdr = get_reader(input).with_context(|| format!("failed to open {}", input))?;
+        self.source(rdr)
+    }
+
+    pub fn open_input<'a>(
+        &self,
+        input: &'a CsvInput,
+    ) -> Result<CsvSource<DecodeReader<Box<dyn Read + 'a>>>> {
+        match input {
+            CsvInput::Path(path) => self.open(path),
+            CsvInput::Memory(data) => self.source(Box::new(data.as_slice())),
+        }
+    }
+
+    // 输入先转换为 utf-8 再交给 csv 解析
+    pub fn source<R: Read>(&self, rdr: R) -> Result<CsvSource<DecodeReader<R>>> {
+        let rdr = DecodeReader::new(rdr, self.encoding)?;
+        CsvSource::new(self.builder().from_reader(rdr), self.has_headers)
+    }
+}
+
+// csv 输入, stdin 只能读取一次, 需要读取两遍 (推断类型) 时先读入内存
+pub enum CsvInput {
+    Path(String),
+    Memory(Vec<u8>),
+}
+
+impl CsvInput {
+    pub fn new(input: &str, rewind: bool) -> Result<Self> {
+        if input == "-" && rewind {
+            Ok(CsvInput::Memory(get_content(input)?))
+        } else {
+            Ok(CsvInput::Path(input.to_string()))
+        }
+    }
+}
+
+// 带列名的 csv 记录源, 没有 header 的文件使用 col_0, col_1 ... 作为列名
+pub struct CsvSource<R> {
+    rdr: Reader<R>,
+    headers: Vec<String>,
+}
+
+impl<R: Read> CsvSource<R> {
+    fn new(mut rdr: Reader<R>, has_headers: bool) -> Result<Self> {
+        // has_headers 为 false 时, headers() 返回第一条记录但不会跳过它
+        let first = rdr.headers()?;
+        let headers = if has_headers {
+            first.iter().map(String::from).collect()
+        } else {
+            (0..first.len()).map(column_name).collect()
+        };
+        Ok(Self { rdr, headers })
+    }
+
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    // 原始的 csv 记录, 用于不需要转换为 json 的场景, 例如 split 和 merge
+    pub fn string_records(&mut self) -> impl Iterator<Item = Result<StringRecord>> + '_ {
+        self.rdr.records().map(|result| Ok(result?))
+    }
+
+    // 逐条读取记录, flexible 模式下多出的字段按位置命名, 缺少的字段直接省略
+    pub fn records(&mut self) -> impl Iterator<Item = Result<Map<String, Value>>> + '_ {
+        self.records_with_line()
+            .map(|result| result.map(|(_, record)| record))
+    }
+
+    // 同 records, 额外返回记录在文件中的起始行号
+    pub fn records_with_line(
+        &mut self,
+    ) -> impl Iterator<Item = Result<(u64, Map<String, Value>)>> + '_ {
+        let headers = &self.headers;
+        self.rdr.records().map(move |result| {
+            let record = result?;
+            let line = record.position().map_or(0, |p| p.line());
+            let map = record
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let name = headers
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| extra_column_name(headers, i));
+                    (name, Value::String(field.to_string()))
+                })
+                .collect();
+            Ok((line, map))
+        })
+    }
+
+    // 逐条读取记录并转换为对应的列类型, 出错时带上记录的行号
+    pub fn typed_records<'a>(
+        &'a mut self,
+        types: &'a ColumnTypes,
+    ) -> impl Iterator<Item = Result<Map<String, Value>>> + 'a {
+        self.records().enumerate().map(move |(i, record)| {
+            let mut record = record?;
+            types
+                .apply(&mut record)
+                .with_context(|| format!("row {}", i + 1))?;
+            Ok(record)
+        })
+    }
+}
+
+pub(crate) fn column_name(index: usize) -> String {
+    format!("col_{}", index)
+}
+
+// flexible 模式下多出的字段按位置命名, 与已有的列名重复时追加 `_` 直到不再重复, 避免覆盖该列的值
+fn extra_column_name(headers: &[String], index: usize) -> String {
+    let mut name = column_name(index);
+    while headers.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
+// csv 转换选项
+#[derive(Debug, Default, Clone)]
+pub struct ConvertOptions {
+    // 推断每列的类型, 需要额外读取一遍输入
+    pub infer: bool,
+    // 指定列类型的 schema 文件, 优先于推断结果
+    pub schema: Option<String>,
+    // 根据 `a.b` / `a[0]` 形式的列名构建嵌套的对象和数组
+    pub nested: bool,
+}
+
+impl ConvertOptions {
+    // 读取输入时使用, infer 需要先把 stdin 读入内存
+    pub fn input(&self, input: &str) -> Result<CsvInput> {
+        CsvInput::new(input, self.infer)
+    }
+
+    pub fn column_types(
+        &self,
+        input: &CsvInput,
+        options: &CsvReaderOptions,
+    ) -> Result<ColumnTypes> {
+        let mut types = if self.infer {
+            infer_types(&mut options.open_input(input)?)?
+        } else {
+            ColumnTypes::default()
+        };
+        types.extend(self.schema_types()?);
+        Ok(types)
+    }
+
+    // 只使用 schema 中指定的类型, 用于单元格本身带有类型的 xlsx/parquet 输入
+    pub fn schema_types(&self) -> Result<ColumnTypes> {
+        match &self.schema {
+            Some(schema) => ColumnTypes::load(schema),
+            None => Ok(ColumnTypes::default()),
+        }
+    }
+
+    // 记录的最终输出形式
+    pub fn render(&self, record: Map<String, Value>) -> Result<Value> {
+        if self.nested {
+            unflatten(record)
+        } else {
+            Ok(Value::Object(record))
+        }
+    }
+}
+
+// 将 csv 文件转换为 json, ndjson 或者 yaml 格式, 可选地将单元格转换为推断或指定的类型
+// 逐条读取 csv 记录并立即写出, 内存占用不随输入文件大小增长 (从 stdin 读取并推断类型时除外)
+pub fn process(
+    input: &str,
+    writer: impl Write,
+    output_format: OutputFormat,
+    options: CsvReaderOptions,
+    convert: ConvertOptions,
+) -> Result<()> {
+    let input = convert.input(input)?;
+    let types = convert.column_types(&input, &options)?;
+    let mut source = options.open_input(&input)?;
+    let mut writer = record_writer(output_format, Box::new(writer));
+    for (i, record) in source.typed_records(&types).enumerate() {
+        let value = convert
+            .render(record?)
+            .with_context(|| format!("row {}", i + 1))?;
+        writer.write(&value)?;
+    }
+    writer.finish()
+}
+
+// 转换记录的类型后逐条写出, 供 xlsx/parquet 等其它表格输入复用
+pub fn write_records(
+    records: impl Iterator<Item = Result<Map<String, Value>>>,
+    writer: impl Write,
+    output_format: OutputFormat,
+    types: &ColumnTypes,
+    convert: &ConvertOptions,
+) -> Result<()> {
+    let mut writer = record_writer(output_format, Box::new(writer));
+    for (i, record) in records.enumerate() {
+        let value = record
+            .and_then(|mut record| {
+                types.apply(&mut record)?;
+                convert.render(record)
+            })
+            .with_context(|| format!("row {}", i + 1))?;
+        writer.write(&value)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headerless_records() -> Result<()> {
+        let options = CsvReaderOptions {
+            delimiter: b';',
+            has_headers: false,
+            comment: Some(b'#'),
+            ..Default::default()
+        };
+        let data = "# exported by vendor\nBuffon;77\nPerin;37\n";
+        let mut source = options.source(data.as_bytes())?;
+        assert_eq!(source.headers(), ["col_0", "col_1"]);
+        let records = source.records().collect::<Result<Vec<_>>>()?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["col_0"], "Buffon");
+        assert_eq!(records[1]["col_1"], "37");
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape_and_flexible() -> Result<()> {
+        let options = CsvReaderOptions {
+            escape: Some(b'\\'),
+            flexible: true,
+            ..Default::default()
+        };
+        let data = "name,quote\nBuffon,\"say \\\"hi\\\"\"\nPerin,ok,extra\n";
+        let mut source = options.source(data.as_bytes())?;
+        let records = source.records().collect::<Result<Vec<_>>>()?;
+        assert_eq!(records[0]["quote"], "say \"hi\"");
+        assert_eq!(records[1]["col_2"], "extra");
+
+        let data = "name,col_2\nPerin,ok,extra\n";
+        let mut source = options.source(data.as_bytes())?;
+        let records = source.records().collect::<Result<Vec<_>>>()?;
+        assert_eq!(records[0]["col_2"], "ok");
+        assert_eq!(records[0]["col_2_"], "extra");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_from_memory() -> Result<()> {
+        let options = CsvReaderOptions::default();
+        let convert = ConvertOptions {
+            infer: true,
+            ..Default::default()
+        };
+        let input = CsvInput::Memory(b"name,kit\nBuffon,77\n".to_vec());
+        let types = convert.column_types(&input, &options)?;
+        let mut source = options.open_input(&input)?;
+        let records = source.typed_records(&types).collect::<Result<Vec<_>>>()?;
+        assert_eq!(records[0]["kit"], 77);
+        Ok(())
+    }
 }
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::{cmp::Ordering, io::Write, str::FromStr};
+
+use crate::cli::OutputFormat;
+use crate::process::csv_expr::{sort_values, Expr};
+use crate::process::csv_processor::{ConvertOptions, CsvReaderOptions};
+use crate::process::record_writer::record_writer;
+
+// 排序的列, `age` 或 `age:asc` 为升序, `age:desc` 为降序
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, descending) = match s.rsplit_once(':') {
+            Some((column, order)) => match order.to_lowercase().as_str() {
+                "asc" => (column, false),
+                "desc" => (column, true),
+                v => return Err(anyhow!("invalid sort order: {}", v)),
+            },
+            None => (s, false),
+        };
+        Ok(Self {
+            column: column.to_string(),
+            descending,
+        })
+    }
+}
+
+// 查询按 过滤 -> 排序 -> offset/limit -> 选择列 -> 重命名 的顺序执行
+#[derive(Debug, Default)]
+pub struct Query {
+    pub select: Vec<String>,
+    pub rename: Vec<(String, String)>,
+    pub filter: Option<Expr>,
+    pub sort: Vec<SortKey>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    fn matches(&self, record: &Map<String, Value>) -> bool {
+        match &self.filter {
+            Some(e) => e.eval(record),
+            None => true,
+        }
+    }
+
+    // null 总是排在最后
+    fn compare(&self, a: &Map<String, Value>, b: &Map<String, Value>) -> Ordering {
+        for key in &self.sort {
+            let a = a.get(&key.column).unwrap_or(&Value::Null);
+            let b = b.get(&key.column).unwrap_or(&Value::Null);
+            let ord = match (a.is_null(), b.is_null()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ => {
+                    let ord = sort_values(a, b);
+                    if key.descending {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                }
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn project(&self, mut record: Map<String, Value>) -> Map<String, Value> {
+        if !self.select.is_empty() {
+            record = self
+                .select
+                .iter()
+                .map(|c| (c.clone(), record.remove(c).unwrap_or(Value::Null)))
+                .collect();
+        }
+        if !self.rename.is_empty() {
+            record = record
+                .into_iter()
+                .map(
+                    |(k, v)| match self.rename.iter().find(|(from, _)| *from == k) {
+                        Some((_, to)) => (to.clone(), v),
+                        None => (k, v),
+                    },
+                )
+                .collect();
+        }
+        record
+    }
+}
+
+// 对 csv 记录进行过滤/排序/分页/选择列, 没有排序时逐条处理并在达到 limit 后停止读取
+pub fn process(
+    input: &str,
+    writer: impl Write,
+    format: OutputFormat,
+    options: CsvReaderOptions,
+    convert: ConvertOptions,
+    query: Query,
+) -> Result<()> {
+    let input = convert.input(input)?;
+    let types = convert.column_types(&input, &options)?;
+    let mut source = options.open_input(&input)?;
+    if !options.flexible {
+        if let Some(c) = query
+            .select
+            .iter()
+            .chain(query.sort.iter().map(|k| &k.column))
+            .chain(query.filter.iter().flat_map(|e| e.columns()))
+            .find(|c| !source.headers().contains(c))
+        {
+            return Err(anyhow!("unknown column: {}", c));
+        }
+    }
+
+    let records = source
+        .typed_records(&types)
+        .filter(|r| r.as_ref().map_or(true, |r| query.matches(r)));
+    let records: Box<dyn Iterator<Item = Result<Map<String, Value>>>> = if query.sort.is_empty() {
+        Box::new(records)
+    } else {
+        let mut rows = records.collect::<Result<Vec<_>>>()?;
+        rows.sort_by(|a, b| query.compare(a, b));
+        Box::new(rows.into_iter().map(Ok))
+    };
+
+    let mut writer = record_writer(format, Box::new(writer));
+    let limit = query.limit.unwrap_or(usize::MAX);
+    for record in records.skip(query.offset).take(limit) {
+        writer.write(&convert.render(query.project(record?))?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(query: Query) -> Result<String> {
+        let mut output = Vec::new();
+        process(
+            "assets/juventus.csv",
+            &mut output,
+            OutputFormat::Ndjson,
+            CsvReaderOptions::default(),
+            ConvertOptions::default(),
+            query,
+        )?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn test_query_pipeline() -> Result<()> {
+        let query = Query {
+            select: vec!["Name".into(), "Kit Number".into()],
+            rename: vec![("Kit Number".into(), "kit".into())],
+            filter: Some(r#"Nationality == "Italy" && Position == "Goalkeeper""#.parse()?),
+            sort: vec!["Kit Number:desc".parse()?],
+            offset: 1,
+            limit: Some(2),
+        };
+        assert_eq!(
+            run(query)?,
+            "{\"Name\":\"Mattia Perin\",\"kit\":\"37\"}\n{\"Name\":\"Carlo Pinsoglio\",\"kit\":\"31\"}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_column() -> Result<()> {
+        let query = Query {
+            select: vec!["Age".into()],
+            ..Default::default()
+        };
+        assert!(run(query).is_err());
+        let query = Query {
+            filter: Some(r#"Nationality == "Italy" || (!(Age > 30))"#.parse()?),
+            ..Default::default()
+        };
+        assert!(run(query).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_mixed_column() -> Result<()> {
+        let query = Query {
+            sort: vec!["v".parse()?],
+            ..Default::default()
+        };
+        let mut rows = ["10", "1a", "2", "b", "", "3"]
+            .iter()
+            .map(|v| Map::from_iter([("v".to_string(), Value::from(*v))]))
+            .chain([Map::from_iter([("v".to_string(), Value::Null)])])
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| query.compare(a, b));
+        let sorted = rows.iter().map(|r| r["v"].clone()).collect::<Vec<_>>();
+        // 数字按数值排在前面, 其余按字符串排序, null 在最后
+        assert_eq!(
+            sorted,
+            [
+                json!("2"),
+                json!("3"),
+                json!("10"),
+                json!(""),
+                json!("1a"),
+                json!("b"),
+                Value::Null
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use csv::WriterBuilder;
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use crate::cli::InputFormat;
+use crate::process::csv_nested::flatten;
+
+// 读取 json 数组/yaml 序列/ndjson 中的对象, 展平后写为 csv
+// 表头为所有记录 key 的并集, 按首次出现的顺序排列, 因此需要先读取全部记录
+pub fn process(
+    reader: impl Read,
+    writer: impl Write,
+    format: InputFormat,
+    delimiter: u8,
+) -> Result<()> {
+    let records = read_records(reader, format)?
+        .iter()
+        .map(flatten)
+        .collect::<Result<Vec<_>>>()?;
+
+    // seen 用于去重, 避免每个 key 都线性查找 headers
+    let mut headers: Vec<&String> = Vec::new();
+    let mut seen = HashSet::new();
+    for record in &records {
+        for key in record.keys() {
+            if seen.insert(key) {
+                headers.push(key);
+            }
+        }
+    }
+
+    let mut wtr = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(writer);
+    wtr.write_record(&headers)?;
+    for record in &records {
+        wtr.write_record(
+            headers
+                .iter()
+                .map(|h| record.get(*h).and_then(Value::as_str).unwrap_or_default()),
+        )?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+fn read_records(reader: impl Read, format: InputFormat) -> Result<Vec<Value>> {
+    let value = match format {
+        InputFormat::Json => serde_json::from_reader(reader)?,
+        InputFormat::Yaml => serde_yaml::from_reader(reader)?,
+        InputFormat::Ndjson => {
+            let mut records = Vec::new();
+            for (i, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record =
+                    serde_json::from_str(&line).map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+                records.push(record);
+            }
+            Value::Array(records)
+        }
+    };
+    match value {
+        Value::Array(records) => Ok(records),
+        record @ Value::Object(_) => Ok(vec![record]),
+        _ => Err(anyhow!("expect an array of objects")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_json() -> Result<()> {
+        let input = r#"[
+            {"name": "Buffon", "kit": 77, "address": {"city": "Turin"}},
+            {"name": "Perin", "tags": ["gk", "loan"], "active": true, "note": null}
+        ]"#;
+        let mut output = Vec::new();
+        process(input.as_bytes(), &mut output, InputFormat::Json, b',')?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "name,kit,address.city,tags[0],tags[1],active,note\n\
+             Buffon,77,Turin,,,,\n\
+             Perin,,,gk,loan,true,\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reverse_ndjson() -> Result<()> {
+        let input = "{\"a\": 1, \"b\": \"x;y\"}\n\n{\"c\": 2.5}\n";
+        let mut output = Vec::new();
+        process(input.as_bytes(), &mut output, InputFormat::Ndjson, b';')?;
+        assert_eq!(String::from_utf8(output)?, "a;b;c\n1;\"x;y\";\n;;2.5\n");
+        Ok(())
+    }
+}
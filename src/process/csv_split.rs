@@ -0,0 +1,403 @@
+use anyhow::{anyhow, Result};
+use csv::StringRecord;
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::process::csv_processor::{CsvInput, CsvReaderOptions};
+
+// 按列拆分时同时打开的文件数上限, 超过时关闭最久未写入的文件, 再次写入时以追加方式重新打开
+const MAX_OPEN_PARTS: usize = 64;
+
+// 拆分方式: 每个文件的记录数, 每个文件的最大字节数, 或者按某一列的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitMode {
+    Rows(usize),
+    Bytes(u64),
+    Column(String),
+}
+
+// 拆分出的一个文件, header 也计入字节数
+struct Part {
+    writer: BufWriter<File>,
+    rows: usize,
+    bytes: u64,
+}
+
+impl Part {
+    // 不覆盖已有的文件, 大小写不敏感的文件系统上只有大小写不同的值也会因此报错
+    fn create(path: &Path, header: Option<&[u8]>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| anyhow!("failed to create {}: {}", path.display(), e))?;
+        let mut part = Self {
+            writer: BufWriter::new(file),
+            rows: 0,
+            bytes: 0,
+        };
+        if let Some(header) = header {
+            part.writer.write_all(header)?;
+            part.bytes += header.len() as u64;
+        }
+        Ok(part)
+    }
+
+    // 重新打开被关闭的文件, header 已经写入过
+    fn append(path: &Path) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(OpenOptions::new().append(true).open(path)?),
+            rows: 0,
+            bytes: 0,
+        })
+    }
+
+    fn write(&mut self, record: &[u8]) -> Result<()> {
+        self.writer.write_all(record)?;
+        self.rows += 1;
+        self.bytes += record.len() as u64;
+        Ok(())
+    }
+
+    // 每个文件至少包含一条记录, 单条记录超过字节上限时单独成为一个文件
+    fn is_full(&self, mode: &SplitMode, next: usize) -> bool {
+        match mode {
+            SplitMode::Rows(n) => self.rows >= *n,
+            SplitMode::Bytes(n) => self.rows > 0 && self.bytes + next as u64 > *n,
+            SplitMode::Column(_) => false,
+        }
+    }
+}
+
+// csv::Writer 不能取出并清空内部的 writer, 通过共享的缓冲区读取编码后的记录
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 先把记录编码为字节才能按字节数拆分, 所有记录复用同一个 writer 和缓冲区
+struct Encoder {
+    writer: csv::Writer<SharedBuffer>,
+    buffer: SharedBuffer,
+}
+
+impl Encoder {
+    fn new(options: &CsvReaderOptions) -> Self {
+        let buffer = SharedBuffer::default();
+        Self {
+            writer: options
+                .writer_builder()
+                .flexible(true)
+                .from_writer(buffer.clone()),
+            buffer,
+        }
+    }
+
+    fn encode(&mut self, record: &StringRecord) -> Result<Ref<'_, Vec<u8>>> {
+        self.buffer.0.borrow_mut().clear();
+        self.writer.write_record(record)?;
+        self.writer.flush()?;
+        Ok(self.buffer.0.borrow())
+    }
+}
+
+// 列的值作为文件名的一部分, 只保留字母, 数字, `-` 和 `_`
+fn file_name(value: &str) -> String {
+    let name = value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() {
+        "empty".to_string()
+    } else {
+        name
+    }
+}
+
+// 将 csv 拆分为多个文件写入 dir, 每个文件都带有 header, 返回生成的文件路径
+// 按记录数或字节数拆分时文件名为 `{prefix}_0001.csv`, 按列拆分时为 `{prefix}_{value}.csv`
+// 清理后文件名相同的值会写入同一个文件; 已经存在的文件不会被覆盖, 而是报错
+pub fn process_split(
+    input: &str,
+    options: CsvReaderOptions,
+    mode: SplitMode,
+    dir: &Path,
+    prefix: &str,
+) -> Result<Vec<PathBuf>> {
+    match mode {
+        SplitMode::Rows(0) | SplitMode::Bytes(0) => {
+            return Err(anyhow!("split size must be greater than 0"))
+        }
+        _ => {}
+    }
+    let mut source = options.open(input)?;
+    let mut encoder = Encoder::new(&options);
+    let header = if options.has_headers {
+        Some(
+            encoder
+                .encode(&StringRecord::from(source.headers()))?
+                .to_vec(),
+        )
+    } else {
+        None
+    };
+    let column = match &mode {
+        SplitMode::Column(c) => Some(
+            source
+                .headers()
+                .iter()
+                .position(|h| h == c)
+                .ok_or_else(|| anyhow!("unknown column: {}", c))?,
+        ),
+        _ => None,
+    };
+    fs::create_dir_all(dir)?;
+
+    let mut paths = Vec::new();
+    let mut current: Option<Part> = None;
+    // 按列拆分时已经创建的文件, 以及按最近写入排序的打开的文件
+    let mut created: HashMap<String, PathBuf> = HashMap::new();
+    let mut parts: Vec<(String, Part)> = Vec::new();
+    for record in source.string_records() {
+        let record = record?;
+        let bytes = encoder.encode(&record)?;
+        if let Some(i) = column {
+            let name = file_name(record.get(i).unwrap_or_default());
+            let mut part = match parts.iter().position(|(n, _)| *n == name) {
+                Some(pos) => parts.remove(pos).1,
+                None => {
+                    if parts.len() >= MAX_OPEN_PARTS {
+                        parts.remove(0).1.writer.flush()?;
+                    }
+                    match created.get(&name) {
+                        Some(path) => Part::append(path)?,
+                        None => {
+                            let path = dir.join(format!("{}_{}.csv", prefix, name));
+                            let part = Part::create(&path, header.as_deref())?;
+                            created.insert(name.clone(), path.clone());
+                            paths.push(path);
+                            part
+                        }
+                    }
+                }
+            };
+            part.write(&bytes)?;
+            parts.push((name, part));
+            continue;
+        }
+        let full = match &current {
+            Some(part) => part.is_full(&mode, bytes.len()),
+            None => true,
+        };
+        if full {
+            if let Some(mut part) = current.take() {
+                part.writer.flush()?;
+            }
+            let path = dir.join(format!("{}_{:04}.csv", prefix, paths.len() + 1));
+            current = Some(Part::create(&path, header.as_deref())?);
+            paths.push(path);
+        }
+        if let Some(part) = current.as_mut() {
+            part.write(&bytes)?;
+        }
+    }
+    for part in current.iter_mut().chain(parts.iter_mut().map(|(_, p)| p)) {
+        part.writer.flush()?;
+    }
+    Ok(paths)
+}
+
+// 合并多个 csv 文件, 列的顺序以第一个文件为准
+// union 为 false 时所有文件的列必须相同 (顺序可以不同), 为 true 时输出所有文件的列, 缺少的单元格为空
+pub fn process_merge(
+    inputs: &[String],
+    writer: impl Write,
+    options: CsvReaderOptions,
+    union: bool,
+) -> Result<()> {
+    if inputs.iter().filter(|i| *i == "-").count() > 1 {
+        return Err(anyhow!("only one of the inputs can be read from stdin"));
+    }
+    // 每次只打开一个文件: 先读取所有文件的 header, 再逐个输出记录; stdin 需要读两遍, 先读入内存
+    let inputs = inputs
+        .iter()
+        .map(|input| Ok((input, CsvInput::new(input, true)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut headers: Vec<String> = Vec::new();
+    for (input, csv_input) in &inputs {
+        let source = options.open_input(csv_input)?;
+        if headers.is_empty() {
+            headers = source.headers().to_vec();
+            continue;
+        }
+        let extra = source
+            .headers()
+            .iter()
+            .filter(|h| !headers.contains(h))
+            .cloned()
+            .collect::<Vec<_>>();
+        let missing = headers
+            .iter()
+            .filter(|h| !source.headers().contains(h))
+            .cloned()
+            .collect::<Vec<_>>();
+        if union {
+            headers.extend(extra);
+        } else if !extra.is_empty() || !missing.is_empty() {
+            return Err(anyhow!(
+                "header of {} does not match {}: missing {:?}, unexpected {:?}, use --union to merge anyway",
+                input,
+                inputs[0].0,
+                missing,
+                extra
+            ));
+        }
+    }
+
+    let mut writer = options.writer_builder().flexible(true).from_writer(writer);
+    if options.has_headers {
+        writer.write_record(&headers)?;
+    }
+    for (_, csv_input) in &inputs {
+        let mut source = options.open_input(csv_input)?;
+        let index = headers
+            .iter()
+            .map(|h| source.headers().iter().position(|c| c == h))
+            .collect::<Vec<_>>();
+        for record in source.string_records() {
+            let record = record?;
+            writer.write_record(
+                index
+                    .iter()
+                    .map(|i| i.and_then(|i| record.get(i)).unwrap_or_default()),
+            )?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const DATA: &str = "name,position\nBuffon,Goalkeeper\nChiellini,Defender\nPerin,Goalkeeper\n";
+
+    fn split(mode: SplitMode) -> Result<Vec<String>> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("in.csv");
+        fs::write(&input, DATA)?;
+        let paths = process_split(
+            &input.to_string_lossy(),
+            CsvReaderOptions::default(),
+            mode,
+            &dir.path().join("out"),
+            "part",
+        )?;
+        paths.iter().map(|p| Ok(fs::read_to_string(p)?)).collect()
+    }
+
+    #[test]
+    fn test_split() -> Result<()> {
+        let parts = split(SplitMode::Rows(2))?;
+        assert_eq!(
+            parts,
+            [
+                "name,position\nBuffon,Goalkeeper\nChiellini,Defender\n",
+                "name,position\nPerin,Goalkeeper\n"
+            ]
+        );
+        // header 14 字节, 每条记录约 20 字节
+        assert_eq!(split(SplitMode::Bytes(40))?.len(), 3);
+        let parts = split(SplitMode::Column("position".into()))?;
+        assert_eq!(
+            parts[0],
+            "name,position\nBuffon,Goalkeeper\nPerin,Goalkeeper\n"
+        );
+        assert_eq!(parts.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_existing_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("in.csv");
+        fs::write(&input, DATA)?;
+        let out = dir.path().join("out");
+        fs::create_dir_all(&out)?;
+        fs::write(out.join("part_Defender.csv"), "keep")?;
+        let result = process_split(
+            &input.to_string_lossy(),
+            CsvReaderOptions::default(),
+            SplitMode::Column("position".into()),
+            &out,
+            "part",
+        );
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(out.join("part_Defender.csv"))?, "keep");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_many_values() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("in.csv");
+        // 值的个数超过同时打开的文件数, 并且交替出现
+        let rows = (0..MAX_OPEN_PARTS * 3)
+            .map(|i| format!("{},{}\n", i, i % (MAX_OPEN_PARTS + 10)))
+            .collect::<String>();
+        fs::write(&input, format!("id,group\n{}", rows))?;
+        let paths = process_split(
+            &input.to_string_lossy(),
+            CsvReaderOptions::default(),
+            SplitMode::Column("group".into()),
+            &dir.path().join("out"),
+            "part",
+        )?;
+        assert_eq!(paths.len(), MAX_OPEN_PARTS + 10);
+        let first = fs::read_to_string(&paths[0])?;
+        assert_eq!(first, "id,group\n0,0\n74,0\n148,0\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() -> Result<()> {
+        let dir = TempDir::new()?;
+        let (a, b) = (dir.path().join("a.csv"), dir.path().join("b.csv"));
+        fs::write(&a, "name,kit\nBuffon,77\n")?;
+        fs::write(&b, "kit,name,club\n1,Szczesny,Juventus\n")?;
+        let inputs = [a, b].map(|p| p.to_string_lossy().to_string());
+        let options = CsvReaderOptions::default();
+        assert!(process_merge(&inputs, Vec::new(), options, false).is_err());
+        let mut output = Vec::new();
+        process_merge(&inputs, &mut output, options, true)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "name,kit,club\nBuffon,77,\nSzczesny,1,Juventus\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, io::Write, str::FromStr};
+
+use crate::cli::OutputFormat;
+use crate::process::csv_processor::CsvReaderOptions;
+use crate::process::record_writer::record_writer;
+
+// group by 的聚合方式, `count`, `sum:col`, `avg:col`, `min:col`, `max:col`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    Count,
+    Sum(String),
+    Avg(String),
+    Min(String),
+    Max(String),
+}
+
+impl FromStr for Aggregate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s.eq_ignore_ascii_case("count") => Ok(Aggregate::Count),
+            Some((func, column)) if !column.is_empty() => {
+                let column = column.to_string();
+                match func.to_lowercase().as_str() {
+                    "sum" => Ok(Aggregate::Sum(column)),
+                    "avg" => Ok(Aggregate::Avg(column)),
+                    "min" => Ok(Aggregate::Min(column)),
+                    "max" => Ok(Aggregate::Max(column)),
+                    v => Err(anyhow!("invalid aggregate function: {}", v)),
+                }
+            }
+            _ => Err(anyhow!("invalid aggregate: {}, expect e.g. `sum:col`", s)),
+        }
+    }
+}
+
+impl Aggregate {
+    fn column(&self) -> Option<&str> {
+        match self {
+            Aggregate::Count => None,
+            Aggregate::Sum(c) | Aggregate::Avg(c) | Aggregate::Min(c) | Aggregate::Max(c) => {
+                Some(c)
+            }
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Aggregate::Count => "count".to_string(),
+            Aggregate::Sum(c) => format!("sum({})", c),
+            Aggregate::Avg(c) => format!("avg({})", c),
+            Aggregate::Min(c) => format!("min({})", c),
+            Aggregate::Max(c) => format!("max({})", c),
+        }
+    }
+}
+
+// 单列的统计, 中位数和去重计数需要保存该列的所有数值和不同的值
+#[derive(Debug, Default)]
+struct ColumnStats {
+    count: usize,
+    nulls: usize,
+    frequencies: HashMap<String, usize>,
+    // 出现非数字的值后不再是数值列, 释放已收集的数值
+    numbers: Option<Vec<f64>>,
+}
+
+impl ColumnStats {
+    fn new() -> Self {
+        Self {
+            numbers: Some(Vec::new()),
+            ..Default::default()
+        }
+    }
+
+    fn observe(&mut self, value: &str) {
+        if value.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+        self.count += 1;
+        *self.frequencies.entry(value.to_string()).or_default() += 1;
+        if let Some(numbers) = &mut self.numbers {
+            match parse_number(value) {
+                Some(n) => numbers.push(n),
+                None => self.numbers = None,
+            }
+        }
+    }
+
+    fn report(mut self, column: &str, top: usize) -> Value {
+        let numbers = self.numbers.take().filter(|n| !n.is_empty());
+        let (min, max, mean, median) = match numbers {
+            Some(mut n) => {
+                n.sort_by(f64::total_cmp);
+                let len = n.len();
+                let median = if len % 2 == 0 {
+                    (n[len / 2 - 1] + n[len / 2]) / 2.0
+                } else {
+                    n[len / 2]
+                };
+                let mean = n.iter().sum::<f64>() / len as f64;
+                (
+                    number(n[0]),
+                    number(n[len - 1]),
+                    number(mean),
+                    number(median),
+                )
+            }
+            None => (Value::Null, Value::Null, Value::Null, Value::Null),
+        };
+        let distinct = self.frequencies.len();
+        let mut frequent = self.frequencies.into_iter().collect::<Vec<_>>();
+        frequent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let top = frequent
+            .into_iter()
+            .take(top)
+            .map(|(value, count)| json!({"value": value, "count": count}))
+            .collect::<Vec<_>>();
+        json!({
+            "column": column,
+            "count": self.count,
+            "nulls": self.nulls,
+            "distinct": distinct,
+            "min": min,
+            "max": max,
+            "mean": mean,
+            "median": median,
+            "top": top,
+        })
+    }
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+// 整数值输出为整数, 避免 77 显示为 77.0
+pub(crate) fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+// 一个分组中每个聚合的累计值
+#[derive(Debug)]
+struct Accumulator {
+    count: usize,
+    values: Vec<(f64, usize, f64, f64)>,
+}
+
+impl Accumulator {
+    fn new(aggregates: &[Aggregate]) -> Self {
+        Self {
+            count: 0,
+            values: vec![(0.0, 0, f64::INFINITY, f64::NEG_INFINITY); aggregates.len()],
+        }
+    }
+
+    fn observe(&mut self, aggregates: &[Aggregate], record: &Map<String, Value>) {
+        self.count += 1;
+        for (agg, (sum, n, min, max)) in aggregates.iter().zip(self.values.iter_mut()) {
+            let value = agg
+                .column()
+                .and_then(|c| record.get(c))
+                .and_then(Value::as_str)
+                .and_then(parse_number);
+            if let Some(v) = value {
+                *sum += v;
+                *n += 1;
+                *min = min.min(v);
+                *max = max.max(v);
+            }
+        }
+    }
+
+    fn report(&self, aggregates: &[Aggregate], group: Map<String, Value>) -> Value {
+        let mut row = group;
+        for (agg, (sum, n, min, max)) in aggregates.iter().zip(&self.values) {
+            let value = match agg {
+                Aggregate::Count => Value::from(self.count),
+                _ if *n == 0 => Value::Null,
+                Aggregate::Sum(_) => number(*sum),
+                Aggregate::Avg(_) => number(sum / *n as f64),
+                Aggregate::Min(_) => number(*min),
+                Aggregate::Max(_) => number(*max),
+            };
+            row.insert(agg.name(), value);
+        }
+        Value::Object(row)
+    }
+}
+
+// 输出每列的统计信息: 非空计数, 空值计数, 去重计数, 数值列的 min/max/mean/median 以及出现最多的 top 个值
+pub fn process_stats(
+    input: &str,
+    writer: impl Write,
+    format: OutputFormat,
+    options: CsvReaderOptions,
+    top: usize,
+) -> Result<()> {
+    let mut source = options.open(input)?;
+    let mut columns: Vec<(String, ColumnStats)> = source
+        .headers()
+        .iter()
+        .map(|h| (h.clone(), ColumnStats::new()))
+        .collect();
+    for record in source.records() {
+        for (column, value) in record? {
+            let value = value.as_str().unwrap_or_default();
+            match columns.iter_mut().find(|(c, _)| *c == column) {
+                Some((_, stats)) => stats.observe(value),
+                None => {
+                    let mut stats = ColumnStats::new();
+                    stats.observe(value);
+                    columns.push((column, stats));
+                }
+            }
+        }
+    }
+    let mut writer = record_writer(format, Box::new(writer));
+    for (column, stats) in columns {
+        writer.write(&stats.report(&column, top))?;
+    }
+    writer.finish()
+}
+
+// 按 group_by 列分组后计算聚合值, 分组按首次出现的顺序输出
+pub fn process_group_by(
+    input: &str,
+    writer: impl Write,
+    format: OutputFormat,
+    options: CsvReaderOptions,
+    group_by: &[String],
+    aggregates: &[Aggregate],
+) -> Result<()> {
+    let mut source = options.open(input)?;
+    if let Some(c) = group_by
+        .iter()
+        .map(String::as_str)
+        .chain(aggregates.iter().filter_map(Aggregate::column))
+        .find(|c| !source.headers().iter().any(|h| h == c))
+    {
+        return Err(anyhow!("unknown column: {}", c));
+    }
+    let mut groups: Vec<(Map<String, Value>, Accumulator)> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    for record in source.records() {
+        let record = record?;
+        let key = group_by
+            .iter()
+            .map(|c| record.get(c).and_then(Value::as_str).unwrap_or_default())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            let group = group_by
+                .iter()
+                .cloned()
+                .zip(key.into_iter().map(Value::String))
+                .collect();
+            groups.push((group, Accumulator::new(aggregates)));
+            groups.len() - 1
+        });
+        groups[i].1.observe(aggregates, &record);
+    }
+    let mut writer = record_writer(format, Box::new(writer));
+    for (group, acc) in groups {
+        writer.write(&acc.report(aggregates, group))?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_stats() {
+        let mut stats = ColumnStats::new();
+        for v in ["3", "1", "", "2", "3"] {
+            stats.observe(v);
+        }
+        let report = stats.report("kit", 1);
+        assert_eq!(
+            report,
+            json!({
+                "column": "kit", "count": 4, "nulls": 1, "distinct": 3,
+                "min": 1, "max": 3, "mean": 2.25, "median": 2.5,
+                "top": [{"value": "3", "count": 2}],
+            })
+        );
+
+        let mut stats = ColumnStats::new();
+        stats.observe("1");
+        stats.observe("Italy");
+        assert_eq!(stats.report("x", 0)["mean"], Value::Null);
+    }
+
+    #[test]
+    fn test_group_by() -> Result<()> {
+        let mut output = Vec::new();
+        let aggregates = ["count".parse()?, "max:Kit Number".parse()?];
+        process_group_by(
+            "assets/juventus.csv",
+            &mut output,
+            OutputFormat::Ndjson,
+            CsvReaderOptions::default(),
+            &["Position".to_string()],
+            &aggregates,
+        )?;
+        let output = String::from_utf8(output)?;
+        assert_eq!(
+            output.lines().next(),
+            Some(r#"{"Position":"Goalkeeper","count":4,"max(Kit Number)":77}"#)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_aggregate() {
+        assert_eq!("COUNT".parse::<Aggregate>().ok(), Some(Aggregate::Count));
+        assert_eq!(
+            "avg:age".parse::<Aggregate>().ok(),
+            Some(Aggregate::Avg("age".into()))
+        );
+        assert!("median:age".parse::<Aggregate>().is_err());
+        assert!("sum".parse::<Aggregate>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::{collections::HashMap, fs::File, io::Read};
+use time::{Date, Month};
+
+use crate::process::csv_processor::CsvSource;
+
+// csv 列的数据类型, 空单元格在任何类型下都会转换为 null
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    String,
+    #[serde(alias = "int")]
+    Integer,
+    Float,
+    #[serde(alias = "bool")]
+    Boolean,
+    Date,
+}
+
+impl ColumnType {
+    pub fn parse(&self, s: &str) -> Result<Value> {
+        if s.is_empty() {
+            return Ok(Value::Null);
+        }
+        let value = match self {
+            ColumnType::String => Some(Value::String(s.to_string())),
+            ColumnType::Integer => s.trim().parse::<i64>().ok().map(Value::from),
+            ColumnType::Float => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number),
+            ColumnType::Boolean => match s.trim().to_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            // json/yaml 没有日期类型, 校验通过后保持 ISO 8601 字符串
+            ColumnType::Date => parse_date(s.trim()).map(|_| Value::String(s.trim().to_string())),
+        };
+        value.ok_or_else(|| anyhow!("invalid {} value: {:?}", self, s))
+    }
+
+    fn matches(&self, s: &str) -> bool {
+        self.parse(s).is_ok()
+    }
+}
+
+impl From<ColumnType> for &'static str {
+    fn from(t: ColumnType) -> Self {
+        match t {
+            ColumnType::String => "string",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Date => "date",
+        }
+    }
+}
+
+impl std::fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+// 解析 YYYY-MM-DD 格式的日期
+fn parse_date(s: &str) -> Option<Date> {
+    let parts = s.split('-').collect::<Vec<_>>();
+    match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+            let month = Month::try_from(m.parse::<u8>().ok()?).ok()?;
+            Date::from_calendar_date(y.parse().ok()?, month, d.parse().ok()?).ok()
+        }
+        _ => None,
+    }
+}
+
+// 每列的类型, 只有出现在其中的列才会做类型转换
+#[derive(Debug, Default, Clone)]
+pub struct ColumnTypes {
+    types: HashMap<String, ColumnType>,
+}
+
+impl ColumnTypes {
+    // 从 yaml/json schema 文件中读取 `列名: 类型` 的映射
+    pub fn load(path: &str) -> Result<Self> {
+        let types = serde_yaml::from_reader(File::open(path)?)
+            .map_err(|e| anyhow!("invalid schema file {}: {}", path, e))?;
+        Ok(Self { types })
+    }
+
+    pub fn get(&self, column: &str) -> Option<ColumnType> {
+        self.types.get(column).copied()
+    }
+
+    // 用 other 中的类型覆盖当前类型
+    pub fn extend(&mut self, other: ColumnTypes) {
+        self.types.extend(other.types);
+    }
+
+    pub fn apply(&self, record: &mut Map<String, Value>) -> Result<()> {
+        for (column, value) in record.iter_mut() {
+            if let (Some(t), Value::String(s)) = (self.types.get(column), &*value) {
+                *value = t
+                    .parse(s)
+                    .map_err(|e| anyhow!("column {:?}: {}", column, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 某一列仍然可能的类型, 每读到一个非空单元格就排除不匹配的类型
+#[derive(Debug)]
+struct Candidates {
+    seen: bool,
+    integer: bool,
+    float: bool,
+    boolean: bool,
+    date: bool,
+}
+
+impl Default for Candidates {
+    fn default() -> Self {
+        Self {
+            seen: false,
+            integer: true,
+            float: true,
+            boolean: true,
+            date: true,
+        }
+    }
+}
+
+impl Candidates {
+    fn observe(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        self.seen = true;
+        // 007, 邮编和电话号码等前导 0 有意义, 转换为数字会丢失, 推断时保留为字符串
+        let leading_zero = has_leading_zero(s);
+        self.integer = self.integer && !leading_zero && ColumnType::Integer.matches(s);
+        self.float = self.float && !leading_zero && ColumnType::Float.matches(s);
+        self.boolean = self.boolean && ColumnType::Boolean.matches(s);
+        self.date = self.date && ColumnType::Date.matches(s);
+    }
+
+    fn resolve(&self) -> ColumnType {
+        match self {
+            Self { seen: false, .. } => ColumnType::String,
+            Self { integer: true, .. } => ColumnType::Integer,
+            Self { float: true, .. } => ColumnType::Float,
+            Self { boolean: true, .. } => ColumnType::Boolean,
+            Self { date: true, .. } => ColumnType::Date,
+            _ => ColumnType::String,
+        }
+    }
+}
+
+// `0` 和 `0.5` 之外以 0 开头的数字, 例如 `007`, `-01`
+fn has_leading_zero(s: &str) -> bool {
+    let digits = s.trim().trim_start_matches(['+', '-']).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+// 读取全部记录推断每列的类型, 只有整列都能解析为某类型时才使用该类型
+pub fn infer_types<R: Read>(source: &mut CsvSource<R>) -> Result<ColumnTypes> {
+    let mut candidates: HashMap<String, Candidates> = HashMap::new();
+    for record in source.records() {
+        for (column, value) in record? {
+            if let Value::String(s) = value {
+                candidates.entry(column).or_default().observe(&s);
+            }
+        }
+    }
+    let types = candidates
+        .into_iter()
+        .map(|(column, c)| (column, c.resolve()))
+        .collect();
+    Ok(ColumnTypes { types })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CsvReaderOptions;
+    use serde_json::json;
+
+    const DATA: &str = "name,kit,height,active,joined,note\n\
+                        Buffon,77,1.92,true,2001-07-03,\n\
+                        Perin,37,1.88,False,2018-07-01,loan\n\
+                        Pinsoglio,,1.94,true,2014-07-01,\n";
+
+    #[test]
+    fn test_infer_types() -> Result<()> {
+        let mut source = CsvReaderOptions::default().source(DATA.as_bytes())?;
+        let types = infer_types(&mut source)?;
+        assert_eq!(types.get("name"), Some(ColumnType::String));
+        assert_eq!(types.get("kit"), Some(ColumnType::Integer));
+        assert_eq!(types.get("height"), Some(ColumnType::Float));
+        assert_eq!(types.get("active"), Some(ColumnType::Boolean));
+        assert_eq!(types.get("joined"), Some(ColumnType::Date));
+        assert_eq!(types.get("note"), Some(ColumnType::String));
+
+        let mut source = CsvReaderOptions::default().source(DATA.as_bytes())?;
+        let mut record = source.records().nth(2).unwrap()?;
+        types.apply(&mut record)?;
+        assert_eq!(
+            Value::Object(record),
+            json!({"name": "Pinsoglio", "kit": null, "height": 1.94, "active": true, "joined": "2014-07-01", "note": null})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_leading_zero() -> Result<()> {
+        let data = "zip,code,ratio,kit\n00501,007,0.5,0\n10001,12,-0.25,10\n";
+        let mut source = CsvReaderOptions::default().source(data.as_bytes())?;
+        let types = infer_types(&mut source)?;
+        assert_eq!(types.get("zip"), Some(ColumnType::String));
+        assert_eq!(types.get("code"), Some(ColumnType::String));
+        assert_eq!(types.get("ratio"), Some(ColumnType::Float));
+        assert_eq!(types.get("kit"), Some(ColumnType::Integer));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_type_mismatch() -> Result<()> {
+        let types = ColumnTypes {
+            types: HashMap::from([("name".to_string(), ColumnType::Integer)]),
+        };
+        let mut source = CsvReaderOptions::default().source(DATA.as_bytes())?;
+        let mut record = source.records().next().unwrap()?;
+        assert!(types.apply(&mut record).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert!(parse_date("2024-02-29").is_some());
+        assert!(parse_date("2023-02-29").is_none());
+        assert!(parse_date("2024-2-1").is_none());
+    }
+}
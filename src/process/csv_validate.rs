@@ -0,0 +1,390 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashSet, fs::File, io::Write};
+
+use crate::process::csv_processor::CsvReaderOptions;
+use crate::process::csv_types::ColumnType;
+
+// 校验规则文件, 例如:
+// columns:
+//   - name: id
+//     type: integer
+//     required: true
+//     unique: true
+//   - name: status
+//     enum: [active, inactive]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationSchema {
+    pub columns: Vec<ColumnRule>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnRule {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: Option<ColumnType>,
+    // 表头中必须有该列, 且每条记录的值不能为空
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub unique: bool,
+    pub pattern: Option<String>,
+    #[serde(rename = "enum")]
+    pub values: Option<Vec<Value>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl ValidationSchema {
+    pub fn load(path: &str) -> Result<Self> {
+        serde_yaml::from_reader(File::open(path)?)
+            .map_err(|e| anyhow!("invalid schema file {}: {}", path, e))
+    }
+}
+
+// 一条校验错误, row 为数据行的序号 (从 1 开始), line 为在文件中的行号, 表头错误时二者为空
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Violation {
+    pub row: Option<usize>,
+    pub line: Option<u64>,
+    pub column: String,
+    pub rule: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub rows: usize,
+    pub violations: usize,
+    pub errors: Vec<Violation>,
+}
+
+// 编译后的单列规则
+struct ColumnValidator<'a> {
+    rule: &'a ColumnRule,
+    pattern: Option<Regex>,
+    values: Option<HashSet<String>>,
+    seen: HashSet<String>,
+}
+
+impl<'a> ColumnValidator<'a> {
+    fn try_new(rule: &'a ColumnRule) -> Result<Self> {
+        let pattern = rule
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| anyhow!("column {}: invalid pattern: {}", rule.name, e))?;
+        let values = rule.values.as_ref().map(|values| {
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                })
+                .collect()
+        });
+        Ok(Self {
+            rule,
+            pattern,
+            values,
+            seen: HashSet::new(),
+        })
+    }
+
+    // 返回 (规则名, 错误信息)
+    fn check(&mut self, value: &str) -> Vec<(&'static str, String)> {
+        let mut errors = Vec::new();
+        if value.is_empty() {
+            if self.rule.required {
+                errors.push(("required", "value is required".to_string()));
+            }
+            return errors;
+        }
+        if let Some(t) = self.rule.column_type {
+            if let Err(e) = t.parse(value) {
+                errors.push(("type", e.to_string()));
+            }
+        }
+        if let Some(re) = &self.pattern {
+            if !re.is_match(value) {
+                errors.push(("pattern", format!("does not match pattern {}", re)));
+            }
+        }
+        if let Some(values) = &self.values {
+            if !values.contains(value) {
+                errors.push(("enum", "is not one of the allowed values".to_string()));
+            }
+        }
+        if self.rule.min.is_some() || self.rule.max.is_some() {
+            // NaN 与任何数比较都为 false, 会同时通过 min 和 max 的检查
+            match value.trim().parse::<f64>().ok().filter(|n| n.is_finite()) {
+                Some(n) if self.rule.min.is_some_and(|min| n < min) => {
+                    errors.push(("min", format!("is less than {}", self.rule.min.unwrap())))
+                }
+                Some(n) if self.rule.max.is_some_and(|max| n > max) => {
+                    errors.push(("max", format!("is greater than {}", self.rule.max.unwrap())))
+                }
+                Some(_) => {}
+                None => errors.push(("range", "is not a finite number".to_string())),
+            }
+        }
+        if self.rule.unique && !self.seen.insert(value.to_string()) {
+            errors.push(("unique", "duplicate value".to_string()));
+        }
+        errors
+    }
+}
+
+// 按 schema 校验所有记录, 收集最多 max_errors 条错误, 错误总数记录在 violations 中
+pub fn validate(
+    input: &str,
+    options: CsvReaderOptions,
+    schema: &ValidationSchema,
+    max_errors: Option<usize>,
+) -> Result<ValidationReport> {
+    let mut source = options.open(input)?;
+    let mut violations = Vec::new();
+    // 按 schema 中的顺序校验, 保证 max_errors 截断后的结果是确定的
+    let mut validators = Vec::new();
+    let mut names = HashSet::new();
+    for rule in &schema.columns {
+        if !names.insert(rule.name.as_str()) {
+            return Err(anyhow!("duplicate rules for column {}", rule.name));
+        }
+        if rule.required && !source.headers().contains(&rule.name) {
+            violations.push(Violation {
+                row: None,
+                line: None,
+                column: rule.name.clone(),
+                rule: "required",
+                value: None,
+                message: "missing required column".to_string(),
+            });
+        }
+        validators.push(ColumnValidator::try_new(rule)?);
+    }
+
+    let mut rows = 0;
+    let mut total = violations.len();
+    let empty = Value::String(String::new());
+    for record in source.records_with_line() {
+        rows += 1;
+        let (line, record): (u64, Map<String, Value>) = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let Some(error) = malformed_row(&e) else {
+                    return Err(e);
+                };
+                total += 1;
+                if max_errors.is_some_and(|max| violations.len() >= max) {
+                    continue;
+                }
+                violations.push(Violation {
+                    row: Some(rows),
+                    line: error.position().map(|p| p.line()),
+                    column: String::new(),
+                    rule: "format",
+                    value: None,
+                    message: error.to_string(),
+                });
+                continue;
+            }
+        };
+        for validator in validators.iter_mut() {
+            let column = &validator.rule.name;
+            let value = record
+                .get(column)
+                .unwrap_or(&empty)
+                .as_str()
+                .unwrap_or_default();
+            for (rule, message) in validator.check(value) {
+                total += 1;
+                if max_errors.is_some_and(|max| violations.len() >= max) {
+                    continue;
+                }
+                violations.push(Violation {
+                    row: Some(rows),
+                    line: Some(line),
+                    column: column.clone(),
+                    rule,
+                    value: Some(value.to_string()),
+                    message,
+                });
+            }
+        }
+    }
+    Ok(ValidationReport {
+        valid: total == 0,
+        rows,
+        violations: total,
+        errors: violations,
+    })
+}
+
+// 字段数不一致或者不是合法的 utf-8 时, csv reader 会跳过该行继续读取, 因此可以作为校验错误报告
+fn malformed_row(e: &anyhow::Error) -> Option<&csv::Error> {
+    e.downcast_ref::<csv::Error>().filter(|e| {
+        matches!(
+            e.kind(),
+            csv::ErrorKind::UnequalLengths { .. } | csv::ErrorKind::Utf8 { .. }
+        )
+    })
+}
+
+// 输出 json 格式的校验报告, 存在错误时返回 Err 以便以非零状态码退出
+pub fn process(
+    input: &str,
+    mut writer: impl Write,
+    options: CsvReaderOptions,
+    schema: &str,
+    max_errors: Option<usize>,
+) -> Result<()> {
+    let schema = ValidationSchema::load(schema)?;
+    let report = validate(input, options, &schema, max_errors)?;
+    serde_json::to_writer_pretty(&mut writer, &report)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    if report.valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "validation failed with {} violation(s)",
+            report.violations
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+columns:
+  - name: Name
+    required: true
+    unique: true
+  - name: Position
+    enum: [Goalkeeper, Defender, Midfielder, Forward]
+  - name: Kit Number
+    type: integer
+    min: 1
+    max: 40
+  - name: Nationality
+    pattern: '^[A-Z][a-z]+$'
+  - name: Age
+    required: true
+"#;
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let schema: ValidationSchema = serde_yaml::from_str(SCHEMA)?;
+        let report = validate(
+            "assets/juventus.csv",
+            CsvReaderOptions::default(),
+            &schema,
+            None,
+        )?;
+        assert!(!report.valid);
+        assert_eq!(report.rows, 27);
+        assert_eq!(report.violations, report.errors.len());
+        // 缺少的 Age 列, 报告在最前面; 并且每行都缺少 Age 的值
+        assert_eq!(report.errors[0].row, None);
+        assert_eq!(report.errors[0].column, "Age");
+        let buffon = report
+            .errors
+            .iter()
+            .find(|v| v.rule == "max")
+            .expect("kit 77 is out of range");
+        assert_eq!(buffon.row, Some(3));
+        assert_eq!(buffon.line, Some(4));
+        assert_eq!(buffon.value.as_deref(), Some("77"));
+        assert!(report.errors.iter().any(|v| v.rule == "pattern"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_errors() -> Result<()> {
+        let schema: ValidationSchema = serde_yaml::from_str(SCHEMA)?;
+        let report = validate(
+            "assets/juventus.csv",
+            CsvReaderOptions::default(),
+            &schema,
+            Some(2),
+        )?;
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.violations > 2);
+        // 先是表头的错误, 然后按行和 schema 中列的顺序
+        let errors = report
+            .errors
+            .iter()
+            .map(|v| (v.row, v.column.as_str(), v.rule))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [(None, "Age", "required"), (Some(1), "Age", "required")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_rules() -> Result<()> {
+        let schema: ValidationSchema =
+            serde_yaml::from_str("columns: [{name: id, unique: true}, {name: id, type: integer}]")?;
+        let result = validate(
+            "assets/juventus.csv",
+            CsvReaderOptions::default(),
+            &schema,
+            None,
+        );
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_rules() -> Result<()> {
+        let rule: ColumnRule = serde_yaml::from_str("{name: id, type: integer, unique: true}")?;
+        let mut validator = ColumnValidator::try_new(&rule)?;
+        assert!(validator.check("1").is_empty());
+        assert_eq!(validator.check("1")[0].0, "unique");
+        assert_eq!(validator.check("x")[0].0, "type");
+        assert!(validator.check("").is_empty());
+
+        let rule: ColumnRule = serde_yaml::from_str("{name: score, min: 0, max: 10}")?;
+        let mut validator = ColumnValidator::try_new(&rule)?;
+        assert!(validator.check("5").is_empty());
+        for value in ["NaN", "inf", "x"] {
+            assert_eq!(validator.check(value)[0].0, "range");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_row() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("ragged.csv");
+        std::fs::write(&path, "id,name\n1,a\n2\n3,c\n")?;
+        let schema: ValidationSchema =
+            serde_yaml::from_str("columns: [{name: id, type: integer}]")?;
+        let report = validate(
+            path.to_str().unwrap(),
+            CsvReaderOptions::default(),
+            &schema,
+            None,
+        )?;
+        // 格式错误的行被记录下来, 后面的行继续校验
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.violations, 1);
+        assert_eq!(report.errors[0].rule, "format");
+        assert_eq!(report.errors[0].row, Some(2));
+        assert_eq!(report.errors[0].line, Some(3));
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Result};
+use calamine::{open_workbook_auto_from_rs, Data, Range, Reader};
+use serde_json::{Map, Value};
+use std::io::{Cursor, Write};
+use time::{Date, Duration, Month};
+
+use crate::cli::OutputFormat;
+use crate::get_content;
+use crate::process::csv_processor::{column_name, write_records, ConvertOptions};
+use crate::process::csv_stats::number;
+
+// 读取 xlsx 中的一个工作表并按 csv 转换的方式输出, sheet 可以是表名或从 0 开始的序号, 默认第一个表
+// 单元格本身带有类型, 因此只应用 schema 中指定的类型, 不做推断
+pub fn process(
+    input: &str,
+    writer: impl Write,
+    output_format: OutputFormat,
+    sheet: Option<&str>,
+    has_headers: bool,
+    convert: ConvertOptions,
+) -> Result<()> {
+    let range = read_sheet(input, sheet)?;
+    let mut rows = range.rows();
+    let headers: Vec<String> = match rows.next() {
+        Some(first) if has_headers => first
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| match cell {
+                Data::Empty => column_name(i),
+                cell => cell.to_string(),
+            })
+            .collect(),
+        _ => {
+            rows = range.rows();
+            Vec::new()
+        }
+    };
+    let records = rows.map(|row| {
+        Ok(row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let name = headers.get(i).cloned().unwrap_or_else(|| column_name(i));
+                (name, cell_value(cell))
+            })
+            .collect::<Map<String, Value>>())
+    });
+    let types = convert.schema_types()?;
+    write_records(records, writer, output_format, &types, &convert)
+}
+
+fn read_sheet(input: &str, sheet: Option<&str>) -> Result<Range<Data>> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(get_content(input)?))
+        .map_err(|e| anyhow!("failed to open workbook {}: {}", input, e))?;
+    let names = workbook.sheet_names();
+    // 优先按表名查找, 找不到时再按序号
+    let index = match sheet {
+        None => 0,
+        Some(s) => match names.iter().position(|n| n == s) {
+            Some(i) => i,
+            None => s
+                .parse::<usize>()
+                .map_err(|_| anyhow!("sheet {} not found, available: {:?}", s, names))?,
+        },
+    };
+    workbook
+        .worksheet_range_at(index)
+        .ok_or_else(|| anyhow!("sheet index {} out of range, available: {:?}", index, names))?
+        .map_err(|e| anyhow!("failed to read sheet {}: {}", index, e))
+}
+
+fn cell_value(cell: &Data) -> Value {
+    match cell {
+        Data::Empty => Value::Null,
+        Data::Int(i) => Value::from(*i),
+        // excel 中的数字都是浮点数, 整数值输出为整数
+        Data::Float(f) => number(*f),
+        Data::Bool(b) => Value::Bool(*b),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => Value::String(s.clone()),
+        Data::DateTime(dt) if dt.is_datetime() => excel_datetime(dt.as_f64())
+            .map(Value::String)
+            .unwrap_or_else(|| number(dt.as_f64())),
+        Data::DateTime(dt) => number(dt.as_f64()),
+        Data::Error(e) => Value::String(e.to_string()),
+    }
+}
+
+// excel 的日期是从 1899-12-30 开始的天数, 小数部分为时间; 没有时间部分时只输出日期
+// excel 支持的最大日期为 9999-12-31, 超出范围的值按数字输出
+const MAX_SERIAL: f64 = 2958466.0;
+
+fn excel_datetime(serial: f64) -> Option<String> {
+    if !(0.0..MAX_SERIAL).contains(&serial) {
+        return None;
+    }
+    let epoch = Date::from_calendar_date(1899, Month::December, 30).ok()?;
+    let days = serial.floor();
+    let date = epoch.checked_add(Duration::days(days as i64))?;
+    let seconds = (((serial - days) * 86400.0).round() as u32).min(86399);
+    if seconds == 0 {
+        return Some(date.to_string());
+    }
+    Some(format!(
+        "{}T{:02}:{:02}:{:02}",
+        date,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excel_datetime() {
+        assert_eq!(excel_datetime(37075.0).as_deref(), Some("2001-07-03"));
+        assert_eq!(
+            excel_datetime(43282.75).as_deref(),
+            Some("2018-07-01T18:00:00")
+        );
+        assert_eq!(
+            excel_datetime(2958465.5).as_deref(),
+            Some("9999-12-31T12:00:00")
+        );
+        for serial in [-1.0, 1e300, f64::NAN, f64::INFINITY] {
+            assert_eq!(excel_datetime(serial), None);
+        }
+    }
+
+    #[test]
+    fn test_process_xlsx() -> Result<()> {
+        let mut output = Vec::new();
+        process(
+            "assets/juventus.xlsx",
+            &mut output,
+            OutputFormat::Ndjson,
+            Some("1"),
+            true,
+            ConvertOptions::default(),
+        )?;
+        let output = String::from_utf8(output)?;
+        assert_eq!(
+            output.lines().next(),
+            Some(
+                r#"{"Name":"Gianluigi Buffon","Kit Number":77,"Active":true,"Joined":"2001-07-03"}"#
+            )
+        );
+        assert_eq!(output.lines().count(), 3);
+        Ok(())
+    }
+}
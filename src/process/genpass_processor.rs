@@ -1,30 +1,127 @@
-use rand::seq::SliceRandom;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use rand::{seq::SliceRandom, CryptoRng, RngCore};
+use serde::Serialize;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    sync::OnceLock,
+};
+use zxcvbn::zxcvbn;
 
+use crate::cli::{GenpassFormat, TokenEncoding};
+
+// 默认字符集本身就去掉了 i l O 0, 避免生成容易看错的密码
 const LOWER: &[u8] = b"abcdefghjkmnopqrstuvwxyz";
 const UPPER: &[u8] = b"ABCDEFGHIJKLMNPQRSTUVWXYZ";
 const NUMBER: &[u8] = b"123456789";
 const SYMBOL: &[u8] = b"!@#$%^&*?~";
+const CONSONANT: &[u8] = b"bcdfghjklmnpqrstvwxz";
+const VOWEL: &[u8] = b"aeiouy";
+// 容易混淆的字符, 开启 exclude_ambiguous 时从所有字符集中去掉
+const AMBIGUOUS: &[u8] = b"iIlLoO01";
+
+// 未达到最低强度时重新生成的最大次数
+const MAX_ATTEMPTS: usize = 100;
+
+// EFF 的长词表, 每行为 `骰子点数<tab>单词`, 共 7776 个单词
+const EFF_LARGE_WORDLIST: &str = include_str!("../../assets/eff_large_wordlist.txt");
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        EFF_LARGE_WORDLIST
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .collect()
+    })
+}
+
+// 生成方式: 从字符集中随机选取, 按模板生成, 或者由辅音和元音交替组成便于发音的密码
+enum Mode {
+    Random,
+    Pattern(Vec<Token>),
+    Pronounceable,
+}
+
+// 模板中的一个字符: 占位符或者原样输出的字符
+enum Token {
+    Class(char),
+    Literal(char),
+}
+
+// 模板占位符: c/C 小写/大写辅音, v/V 小写/大写元音, l/L 小写/大写字母, 9 数字, s 符号, * 整个字符集
+// `\` 转义下一个字符, 其它字符原样输出, 例如 `Cvcc-9999-CVC`
+fn parse_pattern(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => Token::Literal(chars.next().unwrap_or('\\')),
+            'c' | 'C' | 'v' | 'V' | 'l' | 'L' | '9' | 's' | '*' => Token::Class(c),
+            c => Token::Literal(c),
+        });
+    }
+    tokens
+}
 
 // 设置一个 Generator 类型, 用于生成密码
 pub struct Generator {
+    mode: Mode,
     no_upper: bool,
     no_lower: bool,
     no_number: bool,
     no_symbol: bool,
+    symbols: Vec<u8>,
+    include: Vec<u8>,
+    exclude: Vec<u8>,
+    exclude_ambiguous: bool,
+    min_upper: usize,
+    min_lower: usize,
+    min_number: usize,
+    min_symbol: usize,
+    // 每个启用的字符类及其最少出现次数
+    classes: Vec<(Vec<u8>, usize)>,
+    // 模板占位符对应的字符集
+    placeholders: Vec<(char, Vec<u8>)>,
     charset: Vec<u8>,
 }
 
-impl Generator {
-    // 实现 Generator 的 builder 实现, 每个 no_upper, no_lower, no_number, no_symbol 方法都会返回一个 &mut Self, 用于链式调用,且接受一个 bool 参数, 并且返回一个 &mut Self， charst 根据 no_upper, no_lower, no_number, no_symbol 的值来生成, 最后调用 build 方法生成一个 Generator
-    pub fn new() -> Self {
+// 仅用于测试: 由固定 seed 创建的 rng, 相同的 seed 总是生成相同的密码, 不能用于生成真实使用的密码
+// 只在测试或开启 test-rng feature 时编译, 下游 crate 可以在 dev-dependencies 中开启
+#[cfg(any(test, feature = "test-rng"))]
+pub fn seeded_test_rng(seed: u64) -> rand::rngs::StdRng {
+    rand::SeedableRng::seed_from_u64(seed)
+}
+
+impl Default for Generator {
+    fn default() -> Self {
         Self {
+            mode: Mode::Random,
             no_upper: false,
             no_lower: false,
             no_number: false,
             no_symbol: false,
+            symbols: SYMBOL.to_vec(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            exclude_ambiguous: false,
+            min_upper: 0,
+            min_lower: 0,
+            min_number: 0,
+            min_symbol: 0,
+            classes: Vec::new(),
+            placeholders: Vec::new(),
             charset: Vec::new(),
         }
     }
+}
+
+impl Generator {
+    // 实现 Generator 的 builder 实现, 每个 no_upper, no_lower, no_number, no_symbol 方法都会返回一个 &mut Self, 用于链式调用,且接受一个 bool 参数, 并且返回一个 &mut Self， charst 根据 no_upper, no_lower, no_number, no_symbol 的值来生成, 最后调用 build 方法生成一个 Generator
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     pub fn no_lower(mut self, no_lower: bool) -> Self {
         self.no_lower = no_lower;
@@ -46,65 +143,778 @@ impl Generator {
         self
     }
 
-    pub fn build(mut self) -> Self {
-        if !self.no_upper {
-            self.charset.extend_from_slice(UPPER);
+    // 替换默认的符号集, 例如某些系统不接受 `^` 或 `~`, 只支持 ascii 字符
+    pub fn symbols(mut self, symbols: &str) -> Self {
+        self.symbols = symbols.bytes().collect();
+        self
+    }
+
+    // 额外加入字符集的字符, 不属于任何字符类, 不保证出现, 只支持 ascii 字符
+    pub fn include(mut self, include: &str) -> Self {
+        self.include = include.bytes().collect();
+        self
+    }
+
+    // 从所有字符集中去掉的字符
+    pub fn exclude(mut self, exclude: &str) -> Self {
+        self.exclude = exclude.bytes().collect();
+        self
+    }
+
+    pub fn exclude_ambiguous(mut self, exclude_ambiguous: bool) -> Self {
+        self.exclude_ambiguous = exclude_ambiguous;
+        self
+    }
+
+    // 每个字符类的最少字符数, 启用的字符类至少出现一次
+    pub fn min_upper(mut self, min_upper: usize) -> Self {
+        self.min_upper = min_upper;
+        self
+    }
+
+    pub fn min_lower(mut self, min_lower: usize) -> Self {
+        self.min_lower = min_lower;
+        self
+    }
+
+    pub fn min_number(mut self, min_number: usize) -> Self {
+        self.min_number = min_number;
+        self
+    }
+
+    pub fn min_symbol(mut self, min_symbol: usize) -> Self {
+        self.min_symbol = min_symbol;
+        self
+    }
+
+    // 按模板生成, 忽略长度和每个字符类的最少字符数
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.mode = Mode::Pattern(parse_pattern(pattern));
+        self
+    }
+
+    // 由辅音和元音交替组成, 只包含字母, 开启大写字母时首字母大写
+    pub fn pronounceable(mut self, pronounceable: bool) -> Self {
+        if pronounceable {
+            self.mode = Mode::Pronounceable;
         }
-        if !self.no_lower {
-            self.charset.extend_from_slice(LOWER);
+        self
+    }
+
+    fn allowed(&self, c: &u8) -> bool {
+        !(self.exclude.contains(c) || (self.exclude_ambiguous && AMBIGUOUS.contains(c)))
+    }
+
+    // 生成字符集并检查当前的生成方式是否有可用的字符, 避免生成时从空字符集中选取
+    pub fn build(mut self) -> Result<Self> {
+        // 字符集按字节选取, 多字节字符会被拆开, 生成无效的 utf-8
+        for (name, chars) in [
+            ("symbols", &self.symbols),
+            ("include", &self.include),
+            ("exclude", &self.exclude),
+        ] {
+            if !chars.is_ascii() {
+                return Err(anyhow!("{} must only contain ascii characters", name));
+            }
         }
-        if !self.no_number {
-            self.charset.extend_from_slice(NUMBER);
+        let classes = [
+            (
+                "uppercase letters",
+                self.no_upper,
+                UPPER.to_vec(),
+                self.min_upper,
+            ),
+            (
+                "lowercase letters",
+                self.no_lower,
+                LOWER.to_vec(),
+                self.min_lower,
+            ),
+            ("digits", self.no_number, NUMBER.to_vec(), self.min_number),
+            (
+                "symbols",
+                self.no_symbol,
+                self.symbols.clone(),
+                self.min_symbol,
+            ),
+        ];
+        // 去掉排除的字符后为空的字符类视为禁用, 除非指定了最少字符数
+        for (name, disabled, chars, min) in classes {
+            if disabled {
+                continue;
+            }
+            let chars = chars
+                .into_iter()
+                .filter(|c| self.allowed(c))
+                .collect::<Vec<_>>();
+            if chars.is_empty() && min > 0 {
+                return Err(anyhow!(
+                    "at least {} {} required, but all of them are excluded",
+                    min,
+                    name
+                ));
+            }
+            if !chars.is_empty() {
+                self.classes.push((chars, min.max(1)));
+            }
         }
-        if !self.no_symbol {
-            self.charset.extend_from_slice(SYMBOL);
+        let mut charset = self
+            .classes
+            .iter()
+            .flat_map(|(chars, _)| chars.iter().copied())
+            .chain(self.include.iter().copied().filter(|c| self.allowed(c)))
+            .collect::<Vec<_>>();
+        charset.sort_unstable();
+        charset.dedup();
+        self.placeholders = [
+            ('c', CONSONANT.to_vec()),
+            ('C', CONSONANT.to_ascii_uppercase()),
+            ('v', VOWEL.to_vec()),
+            ('V', VOWEL.to_ascii_uppercase()),
+            ('l', LOWER.to_vec()),
+            ('L', UPPER.to_vec()),
+            ('9', NUMBER.to_vec()),
+            ('s', self.symbols.clone()),
+            ('*', charset.clone()),
+        ]
+        .into_iter()
+        .map(|(c, chars)| (c, chars.into_iter().filter(|c| self.allowed(c)).collect()))
+        .collect();
+        self.charset = charset;
+
+        let required = match &self.mode {
+            Mode::Random => vec!['*'],
+            Mode::Pattern(tokens) => tokens
+                .iter()
+                .filter_map(|token| match token {
+                    Token::Class(c) => Some(*c),
+                    Token::Literal(_) => None,
+                })
+                .collect(),
+            Mode::Pronounceable => self.syllables(2).collect(),
+        };
+        match required.iter().find(|c| self.placeholder(**c).is_empty()) {
+            Some('*') => Err(anyhow!(
+                "character set is empty, enable at least one character class or include some characters"
+            )),
+            Some(c) => Err(anyhow!("no characters left for placeholder '{}'", c)),
+            None => Ok(self),
         }
-        self
     }
 
-    // 生成密码, 接受一个长度参数, 返回一个 Vec<u8>
-    pub fn generate(&self, length: u8) -> Vec<u8> {
-        let mut rng = rand::thread_rng();
-        let mut password = Vec::with_capacity(length as usize);
-        if !self.no_upper {
-            password.push(*UPPER.choose(&mut rng).unwrap());
+    fn placeholder(&self, c: char) -> &[u8] {
+        self.placeholders
+            .iter()
+            .find(|(p, _)| *p == c)
+            .map(|(_, chars)| chars.as_slice())
+            .unwrap_or_default()
+    }
+
+    // 便于发音的密码中每个位置的占位符, 辅音开头, 辅音和元音交替
+    fn syllables(&self, length: usize) -> impl Iterator<Item = char> + '_ {
+        (0..length).map(|i| match (i, i % 2) {
+            (0, _) if !self.no_upper => 'C',
+            (_, 0) => 'c',
+            _ => 'v',
+        })
+    }
+
+    // 满足每个字符类最少字符数所需的最短长度, 只适用于随机生成
+    pub fn min_length(&self) -> usize {
+        match self.mode {
+            Mode::Random => self.classes.iter().map(|(_, min)| min).sum(),
+            _ => 0,
         }
-        if !self.no_lower {
-            password.push(*LOWER.choose(&mut rng).unwrap());
+    }
+
+    // 密码的熵 (bit), 按每个位置可选字符集的大小累加
+    // 随机生成时每个字符类最少字符数对应的字符只从该字符类中选取, 其余字符从整个字符集中选取
+    pub fn entropy(&self, length: usize) -> f64 {
+        let bits = |c| (self.placeholder(c).len() as f64).log2();
+        match &self.mode {
+            Mode::Random => {
+                let required = self
+                    .classes
+                    .iter()
+                    .map(|(chars, min)| *min as f64 * (chars.len() as f64).log2())
+                    .sum::<f64>();
+                let rest = length.saturating_sub(self.min_length()) as f64;
+                required + rest * (self.charset.len() as f64).log2()
+            }
+            Mode::Pattern(tokens) => tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Class(c) => bits(*c),
+                    Token::Literal(_) => 0.0,
+                })
+                .sum(),
+            Mode::Pronounceable => self.syllables(length).map(bits).sum(),
         }
-        if !self.no_number {
-            password.push(*NUMBER.choose(&mut rng).unwrap());
+    }
+
+    // 生成密码, 接受一个长度参数, 返回一个 Vec<u8>; 按模板生成时忽略长度
+    pub fn generate(&self, length: usize) -> Vec<u8> {
+        self.generate_with(length, &mut rand::thread_rng())
+    }
+
+    // 使用指定的 rng 生成密码, build 已经保证用到的字符集都不为空
+    pub fn generate_with<R: RngCore + CryptoRng>(&self, length: usize, rng: &mut R) -> Vec<u8> {
+        let mut pick = |c| *self.placeholder(c).choose(rng).unwrap();
+        match &self.mode {
+            Mode::Random => {}
+            Mode::Pattern(tokens) => {
+                let mut password = Vec::new();
+                for token in tokens {
+                    match token {
+                        Token::Class(c) => password.push(pick(*c)),
+                        Token::Literal(c) => {
+                            password.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+                        }
+                    }
+                }
+                return password;
+            }
+            Mode::Pronounceable => return self.syllables(length).map(pick).collect(),
         }
-        if !self.no_symbol {
-            password.push(*SYMBOL.choose(&mut rng).unwrap());
+        let mut password = Vec::with_capacity(length);
+        for (chars, min) in &self.classes {
+            for _ in 0..*min {
+                password.push(*chars.choose(rng).unwrap());
+            }
         }
-        if length as usize <= password.len() {
-            password.shuffle(&mut rng);
-            password.drain(0..length as usize).collect()
+        if length <= password.len() {
+            password.shuffle(rng);
+            password.drain(0..length).collect()
         } else {
-            for _ in 0..(length - password.len() as u8) {
-                password.push(*self.charset.choose(&mut rng).unwrap());
+            for _ in password.len()..length {
+                password.push(*self.charset.choose(rng).unwrap());
             }
-            password.shuffle(&mut rng);
+            password.shuffle(rng);
             password
         }
     }
 }
 
-// 使用 genpass 生成密码, 生成密码的时候可以指定密码的长度, 是否包含大写字母, 小写字母, 数字, 特殊字符等
-// 生成的密码可以直接输出到 stdout
+// 由词表中随机选取的单词组成的密码短语 (diceware), 便于记忆, 例如 `Crusader-Unmixed-Gallon-Reborn7`
+pub struct Passphrase {
+    words: usize,
+    separator: String,
+    capitalize: bool,
+    digit: bool,
+    symbol: bool,
+    symbols: Vec<u8>,
+}
+
+impl Passphrase {
+    pub fn new(words: usize) -> Self {
+        Self {
+            words,
+            separator: "-".to_string(),
+            capitalize: false,
+            digit: false,
+            symbol: false,
+            symbols: SYMBOL.to_vec(),
+        }
+    }
+
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    pub fn capitalize(mut self, capitalize: bool) -> Self {
+        self.capitalize = capitalize;
+        self
+    }
+
+    // 在末尾追加一个随机数字
+    pub fn digit(mut self, digit: bool) -> Self {
+        self.digit = digit;
+        self
+    }
+
+    // 在末尾追加一个随机符号
+    pub fn symbol(mut self, symbol: bool) -> Self {
+        self.symbol = symbol;
+        self
+    }
+
+    // 替换追加符号时使用的符号集, 与 Generator::symbols 相同, 只支持 ascii 字符
+    pub fn symbols(mut self, symbols: &str) -> Self {
+        self.symbols = symbols.bytes().collect();
+        self
+    }
+
+    // 检查追加符号时符号集不为空且只包含 ascii 字符
+    pub fn check(&self) -> Result<()> {
+        if !self.symbols.is_ascii() {
+            return Err(anyhow!("symbols must only contain ascii characters"));
+        }
+        if self.symbol && self.symbols.is_empty() {
+            return Err(anyhow!("symbol set is empty, cannot append a symbol"));
+        }
+        Ok(())
+    }
+
+    pub fn generate(&self) -> String {
+        self.generate_with(&mut rand::thread_rng())
+    }
+
+    pub fn generate_with<R: RngCore + CryptoRng>(&self, rng: &mut R) -> String {
+        let words = (0..self.words)
+            .filter_map(|_| wordlist().choose(rng))
+            .map(|word| {
+                if self.capitalize {
+                    capitalize(word)
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut passphrase = words.join(&self.separator);
+        if self.digit {
+            passphrase.push(*NUMBER.choose(rng).unwrap() as char);
+        }
+        if let Some(c) = self.symbols.choose(rng).filter(|_| self.symbol) {
+            passphrase.push(*c as char);
+        }
+        passphrase
+    }
+
+    // 熵 (bit) 只来自随机选择的单词和追加的字符, 分隔符和首字母大写是固定的, 不增加熵
+    pub fn entropy(&self) -> f64 {
+        let mut entropy = self.words as f64 * (wordlist().len() as f64).log2();
+        if self.digit {
+            entropy += (NUMBER.len() as f64).log2();
+        }
+        if self.symbol {
+            entropy += (self.symbols.len() as f64).log2();
+        }
+        entropy
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// 生成的密码以及 zxcvbn 的强度评估, 破解时间按离线慢哈希 (每秒 1 万次) 估算
+#[derive(Debug, Serialize)]
+pub struct PasswordReport {
+    pub password: String,
+    pub score: u8,
+    pub crack_time: String,
+    pub entropy: f64,
+}
+
+impl PasswordReport {
+    pub fn new(password: String, entropy: f64) -> Result<Self> {
+        let estimate = zxcvbn(&password, &[])?;
+        let crack_time = estimate
+            .crack_times()
+            .offline_slow_hashing_1e4_per_second()
+            .to_string();
+        Ok(Self {
+            score: estimate.score(),
+            crack_time,
+            entropy: (entropy * 100.0).round() / 100.0,
+            password,
+        })
+    }
+}
+
+// zxcvbn 对一个已有密码的评估, 包括警告和改进建议
+#[derive(Debug, Serialize)]
+pub struct PasswordCheck {
+    pub score: u8,
+    pub crack_time: String,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+impl PasswordCheck {
+    pub fn new(password: &str) -> Result<Self> {
+        let estimate = zxcvbn(password, &[])?;
+        let feedback = estimate.feedback().as_ref();
+        Ok(Self {
+            score: estimate.score(),
+            crack_time: estimate
+                .crack_times()
+                .offline_slow_hashing_1e4_per_second()
+                .to_string(),
+            warning: feedback.and_then(|f| f.warning()).map(|w| w.to_string()),
+            suggestions: feedback
+                .map(|f| f.suggestions().iter().map(|s| s.to_string()).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+// 重复生成直到 zxcvbn 的评分达到 min_score, 超过 MAX_ATTEMPTS 次仍未达到时返回错误
+fn generate_with_score(
+    min_score: u8,
+    mut generate: impl FnMut() -> Result<PasswordReport>,
+) -> Result<PasswordReport> {
+    for _ in 0..MAX_ATTEMPTS {
+        let report = generate()?;
+        if report.score >= min_score {
+            return Ok(report);
+        }
+    }
+    Err(anyhow!(
+        "failed to generate a password with score >= {} after {} attempts, try a longer length or more character classes",
+        min_score,
+        MAX_ATTEMPTS
+    ))
+}
+
+// 使用 generator 生成 count 个密码, 每个密码的 zxcvbn 评分都不低于 min_score
 pub fn process(
-    no_upper: bool,
-    no_lower: bool,
-    no_number: bool,
-    no_symbol: bool,
-    length: u8,
-) -> Vec<u8> {
-    let generator = Generator::new()
-        .no_upper(no_upper)
-        .no_lower(no_lower)
-        .no_number(no_number)
-        .no_symbol(no_symbol)
-        .build();
-    generator.generate(length)
+    generator: &Generator,
+    length: usize,
+    count: usize,
+    min_score: u8,
+) -> Result<Vec<PasswordReport>> {
+    if generator.min_length() > length {
+        return Err(anyhow!(
+            "length {} is shorter than the {} characters required by the per-class minimums",
+            length,
+            generator.min_length()
+        ));
+    }
+    (0..count)
+        .map(|_| {
+            generate_with_score(min_score, || {
+                let password = String::from_utf8(generator.generate(length))?;
+                PasswordReport::new(password, generator.entropy(length))
+            })
+        })
+        .collect()
+}
+
+// 生成 count 个 zxcvbn 评分不低于 min_score 的密码短语
+pub fn process_passphrase(
+    passphrase: &Passphrase,
+    count: usize,
+    min_score: u8,
+) -> Result<Vec<PasswordReport>> {
+    passphrase.check()?;
+    (0..count)
+        .map(|_| {
+            generate_with_score(min_score, || {
+                PasswordReport::new(passphrase.generate(), passphrase.entropy())
+            })
+        })
+        .collect()
+}
+
+// 生成 count 个由 bytes 个随机字节编码而成的 token, 熵为 bytes * 8
+pub fn process_token(
+    bytes: usize,
+    encoding: TokenEncoding,
+    count: usize,
+) -> Result<Vec<PasswordReport>> {
+    if bytes == 0 {
+        return Err(anyhow!("token must be at least 1 byte"));
+    }
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let mut buf = vec![0u8; bytes];
+            rng.fill_bytes(&mut buf);
+            PasswordReport::new(encode_token(&buf, encoding), (bytes * 8) as f64)
+        })
+        .collect()
+}
+
+fn encode_token(bytes: &[u8], encoding: TokenEncoding) -> String {
+    match encoding {
+        TokenEncoding::Hex => HEXLOWER.encode(bytes),
+        TokenEncoding::Base64 => URL_SAFE_NO_PAD.encode(bytes),
+        TokenEncoding::Base32 => BASE32_NOPAD.encode(bytes),
+    }
+}
+
+// 逐行读取密码并输出评分, 警告和建议; 输出中不包含密码本身, 只用行号标识, 空行跳过
+pub fn process_check(reader: impl Read, mut writer: impl Write) -> Result<()> {
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let password = line.trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        let check = PasswordCheck::new(password)?;
+        writeln!(
+            writer,
+            "line {}: score {}/4, crack time {}",
+            i + 1,
+            check.score,
+            check.crack_time
+        )?;
+        if let Some(warning) = &check.warning {
+            writeln!(writer, "  warning: {}", warning)?;
+        }
+        for suggestion in &check.suggestions {
+            writeln!(writer, "  suggestion: {}", suggestion)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// 输出密码: plain 每行一个密码, json 和 csv 包含强度评估
+pub fn write_reports(
+    reports: &[PasswordReport],
+    format: GenpassFormat,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        GenpassFormat::Plain => {
+            for report in reports {
+                writeln!(writer, "{}", report.password)?;
+            }
+        }
+        GenpassFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, reports)?;
+            writeln!(writer)?;
+        }
+        GenpassFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(&mut writer);
+            for report in reports {
+                writer.serialize(report)?;
+            }
+            writer.flush()?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generator_charset() -> Result<()> {
+        let generator = Generator::new()
+            .symbols("-_")
+            .include(".")
+            .exclude("abc")
+            .exclude_ambiguous(true)
+            .min_number(3)
+            .min_symbol(2)
+            .build()?;
+        assert_eq!(generator.min_length(), 7);
+        for _ in 0..20 {
+            let password = generator.generate(10);
+            assert_eq!(password.len(), 10);
+            assert!(password.iter().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(password.iter().filter(|c| b"-_".contains(c)).count() >= 2);
+            assert!(password
+                .iter()
+                .all(|c| !b"abc".contains(c) && !AMBIGUOUS.contains(c)));
+            assert!(password
+                .iter()
+                .all(|c| c.is_ascii_alphanumeric() || b"-_.".contains(c)));
+        }
+        assert!(process(&generator, 6, 1, 0).is_err());
+        // 3 个数字 + 2 个符号 + 大小写字母各 1 个, 其余 3 个从整个字符集中选取
+        // 去掉 abc 和易混淆字符后, 数字剩 8 个, 大写字母剩 23 个, 小写字母剩 20 个
+        let expected = 3.0 * 8f64.log2()
+            + 2.0
+            + 23f64.log2()
+            + 20f64.log2()
+            + 3.0 * (generator.charset.len() as f64).log2();
+        assert!((generator.entropy(10) - expected).abs() < 1e-9);
+        let charset = Generator::new().build()?.charset;
+        assert!(!charset.iter().any(|c| b"ilO0".contains(c)));
+        assert!(charset.contains(&b'1') && charset.contains(&b'I'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generator_build() -> Result<()> {
+        let none = Generator::default()
+            .no_upper(true)
+            .no_lower(true)
+            .no_number(true)
+            .no_symbol(true);
+        assert!(none.build().is_err());
+        let only_include = Generator::default()
+            .no_upper(true)
+            .no_lower(true)
+            .no_number(true)
+            .no_symbol(true)
+            .include("xyz");
+        assert!(only_include
+            .build()?
+            .generate(8)
+            .iter()
+            .all(|c| b"xyz".contains(c)));
+        assert!(Generator::new().exclude("0123456789").build().is_ok());
+        assert!(Generator::new().symbols("é").build().is_err());
+        assert!(Generator::new().include("ü").build().is_err());
+        assert!(Generator::new()
+            .exclude("0123456789")
+            .min_number(1)
+            .build()
+            .is_err());
+        assert!(Generator::new()
+            .pattern("9-v")
+            .exclude("aeiouy")
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seeded_rng() -> Result<()> {
+        let generator = Generator::default().build()?;
+        let a = generator.generate_with(24, &mut seeded_test_rng(42));
+        let b = generator.generate_with(24, &mut seeded_test_rng(42));
+        assert_eq!(a, b);
+        assert_ne!(a, generator.generate_with(24, &mut seeded_test_rng(43)));
+        let passphrase = Passphrase::new(4);
+        assert_eq!(
+            passphrase.generate_with(&mut seeded_test_rng(7)),
+            passphrase.generate_with(&mut seeded_test_rng(7))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_password() -> Result<()> {
+        let reports = process(&Generator::new().build()?, 600, 1, 0)?;
+        assert_eq!(reports[0].password.len(), 600);
+        assert_eq!(Generator::new().build()?.generate(2).len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_token() -> Result<()> {
+        assert_eq!(
+            encode_token(&[0xde, 0xad, 0xbe, 0xef], TokenEncoding::Hex),
+            "deadbeef"
+        );
+        assert_eq!(encode_token(b"foobar", TokenEncoding::Base32), "MZXW6YTBOI");
+        assert_eq!(encode_token(&[0xfb, 0xff], TokenEncoding::Base64), "-_8");
+        let reports = process_token(32, TokenEncoding::Hex, 2)?;
+        assert_eq!(reports[0].password.len(), 64);
+        assert_eq!(reports[0].entropy, 256.0);
+        assert_ne!(reports[0].password, reports[1].password);
+        assert!(process_token(0, TokenEncoding::Hex, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generator_pattern() -> Result<()> {
+        let generator = Generator::new().pattern("Cvcc-9999-\\s*s").build()?;
+        let password = String::from_utf8(generator.generate(0))?;
+        let chars = password.chars().collect::<Vec<_>>();
+        assert_eq!(chars.len(), 13);
+        assert!(CONSONANT.contains(&(chars[0].to_ascii_lowercase() as u8)));
+        assert!(chars[0].is_ascii_uppercase());
+        assert!(VOWEL.contains(&(chars[1] as u8)));
+        assert_eq!(chars[4], '-');
+        assert!(chars[5..9].iter().all(|c| c.is_ascii_digit()));
+        assert_eq!(&password[9..11], "-s");
+        assert!(SYMBOL.contains(&(chars[12] as u8)));
+        // 4 个数字 + 2 个辅音 + 1 个大写辅音 + 1 个元音 + 1 个符号 + 1 个任意字符
+        let expected = 4.0 * (NUMBER.len() as f64).log2()
+            + 3.0 * 20f64.log2()
+            + 6f64.log2()
+            + 10f64.log2()
+            + (generator.charset.len() as f64).log2();
+        assert!((generator.entropy(0) - expected).abs() < 1e-9);
+
+        let generator = Generator::new()
+            .pronounceable(true)
+            .exclude_ambiguous(true)
+            .build()?;
+        let password = generator.generate(9);
+        assert_eq!(password.len(), 9);
+        assert!(password[0].is_ascii_uppercase());
+        for (i, c) in password.iter().enumerate().skip(1) {
+            let class = if i % 2 == 0 { CONSONANT } else { VOWEL };
+            assert!(class.contains(c) && !AMBIGUOUS.contains(c));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wordlist() {
+        assert_eq!(wordlist().len(), 7776);
+        assert_eq!(wordlist()[0], "abacus");
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let passphrase = Passphrase::new(5)
+            .separator(" ")
+            .capitalize(true)
+            .digit(true);
+        let generated = passphrase.generate();
+        let words = generated.split(' ').collect::<Vec<_>>();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert!(generated.ends_with(|c: char| c.is_ascii_digit()));
+        // 5 * log2(7776) + log2(9)
+        assert!((passphrase.entropy() - 67.79).abs() < 0.01);
+
+        let passphrase = Passphrase::new(3).symbol(true).symbols("-_");
+        assert!(passphrase.generate().ends_with(['-', '_']));
+        assert!((passphrase.entropy() - (3.0 * 7776f64.log2() + 1.0)).abs() < 1e-9);
+        assert!(process_passphrase(&Passphrase::new(3).symbol(true).symbols(""), 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_write_reports() -> Result<()> {
+        let reports = process(&Generator::new().build()?, 20, 3, 0)?;
+        assert_eq!(reports.len(), 3);
+        assert!(reports.iter().all(|r| r.password.len() == 20));
+
+        let report = PasswordReport::new("password".to_string(), 37.6)?;
+        let mut output = Vec::new();
+        write_reports(&[report], GenpassFormat::Csv, &mut output)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "password,score,crack_time,entropy\npassword,0,less than a second,37.6\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_score() -> Result<()> {
+        let reports = process(&Generator::new().build()?, 12, 5, 4)?;
+        assert!(reports.iter().all(|r| r.score == 4));
+        // 3 位纯数字的密码不可能达到 4 分
+        let digits = Generator::new()
+            .no_upper(true)
+            .no_lower(true)
+            .no_symbol(true)
+            .build()?;
+        assert!(process(&digits, 3, 1, 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_check() -> Result<()> {
+        let mut output = Vec::new();
+        process_check(&b"password\n\nKV5HU5^xY9mz?JwV\n"[..], &mut output)?;
+        let output = String::from_utf8(output)?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "line 1: score 0/4, crack time less than a second");
+        assert!(lines[1].starts_with("  warning: This is a top-10 common password"));
+        assert!(lines.iter().any(|l| l.starts_with("  suggestion: ")));
+        assert_eq!(
+            lines.last(),
+            Some(&"line 3: score 4/4, crack time centuries")
+        );
+        assert!(!output.contains("password\n"));
+        Ok(())
+    }
 }
@@ -1,14 +1,59 @@
 mod base64_processor;
+mod csv_encoding;
+mod csv_expr;
+mod csv_join;
+mod csv_mask;
+mod csv_nested;
+mod csv_parquet;
 mod csv_processor;
+mod csv_query;
+mod csv_reverse;
+mod csv_split;
+mod csv_stats;
+mod csv_types;
+mod csv_validate;
+mod csv_xlsx;
 mod genpass_processor;
 mod http_serve;
 mod jwt;
+mod otp;
+mod record_writer;
 mod text;
 
 pub use base64_processor::process_decode as process_b64decode;
 pub use base64_processor::process_encode as process_b64encode;
-pub use csv_processor::process as process_csv;
-pub use genpass_processor::process as process_genpass;
+pub use csv_encoding::DecodeReader;
+pub use csv_expr::Expr;
+pub use csv_join::{process_diff as process_csv_diff, process_join as process_csv_join};
+pub use csv_mask::{process as process_csv_mask, Masker};
+pub use csv_nested::{flatten as flatten_record, unflatten as unflatten_record};
+pub use csv_parquet::process as process_csv_parquet;
+pub use csv_processor::{
+    process as process_csv, write_records, ConvertOptions, CsvInput, CsvReaderOptions, CsvSource,
+};
+pub use csv_query::{process as process_csv_query, Query, SortKey};
+pub use csv_reverse::process as process_csv_reverse;
+pub use csv_split::{
+    process_merge as process_csv_merge, process_split as process_csv_split, SplitMode,
+};
+pub use csv_stats::{
+    process_group_by as process_csv_group_by, process_stats as process_csv_stats, Aggregate,
+};
+pub use csv_types::{infer_types, ColumnType, ColumnTypes};
+pub use csv_validate::{
+    process as process_csv_validate, validate as validate_csv, ColumnRule, ValidationReport,
+    ValidationSchema, Violation,
+};
+pub use csv_xlsx::process as process_csv_xlsx;
+#[cfg(any(test, feature = "test-rng"))]
+pub use genpass_processor::seeded_test_rng;
+pub use genpass_processor::{
+    process as process_genpass, process_check as process_genpass_check, process_passphrase,
+    process_token as process_genpass_token, write_reports as write_password_reports, Generator,
+    Passphrase, PasswordCheck, PasswordReport,
+};
 pub use http_serve::process_http_serve;
 pub use jwt::{process_sign as process_jwt_sign, process_verify as process_jwt_verify};
+pub use otp::{generate_secret as generate_otp_secret, Otp};
+pub use record_writer::{record_writer, RecordWriter};
 pub use text::{process_decrypt, process_encrypt, process_generate, process_sign, process_verify};
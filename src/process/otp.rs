@@ -0,0 +1,278 @@
+use anyhow::{anyhow, Result};
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use ring::hmac;
+use url::Url;
+
+use crate::cli::OtpAlgorithm;
+
+// HOTP (RFC 4226) 和 TOTP (RFC 6238) 一次性密码, TOTP 的计数器为 unix 时间戳除以 period
+#[derive(Debug, Clone)]
+pub struct Otp {
+    secret: Vec<u8>,
+    algorithm: OtpAlgorithm,
+    digits: u32,
+    period: u64,
+}
+
+// 生成随机密钥, 默认 20 字节, 与 SHA1 的输出长度相同
+pub fn generate_secret(bytes: usize) -> Vec<u8> {
+    let mut secret = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+impl Otp {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self {
+            secret,
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+        }
+    }
+
+    // 认证器中的 base32 密钥通常会分组显示, 忽略空格, `-`, 大小写和末尾的 `=`
+    pub fn from_base32(secret: &str) -> Result<Self> {
+        let secret = secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_uppercase();
+        let secret = BASE32_NOPAD
+            .decode(secret.trim_end_matches('=').as_bytes())
+            .map_err(|e| anyhow!("invalid base32 secret: {}", e))?;
+        if secret.is_empty() {
+            return Err(anyhow!("otp secret is empty"));
+        }
+        Ok(Self::new(secret))
+    }
+
+    // 解析 otpauth:// 格式的 uri, hotp 返回 uri 中的计数器, totp 返回 None
+    pub fn from_uri(uri: &str) -> Result<(Self, Option<u64>)> {
+        let url = Url::parse(uri).map_err(|e| anyhow!("invalid otpauth uri: {}", e))?;
+        if url.scheme() != "otpauth" {
+            return Err(anyhow!("invalid otpauth uri scheme: {}", url.scheme()));
+        }
+        let hotp = match url.host_str() {
+            Some("totp") => false,
+            Some("hotp") => true,
+            kind => return Err(anyhow!("invalid otp type: {}", kind.unwrap_or_default())),
+        };
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+        };
+        let secret = param("secret").ok_or_else(|| anyhow!("otpauth uri has no secret"))?;
+        let mut otp = Self::from_base32(&secret)?;
+        if let Some(algorithm) = param("algorithm") {
+            otp = otp.algorithm(algorithm.parse()?);
+        }
+        if let Some(digits) = param("digits") {
+            otp = otp.digits(digits.parse()?)?;
+        }
+        if let Some(period) = param("period") {
+            otp = otp.period(period.parse()?)?;
+        }
+        let counter = match param("counter") {
+            Some(counter) if hotp => Some(counter.parse()?),
+            None if hotp => return Err(anyhow!("hotp uri has no counter")),
+            _ => None,
+        };
+        Ok((otp, counter))
+    }
+
+    pub fn algorithm(mut self, algorithm: OtpAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    // RFC 4226 要求至少 6 位, 超过 8 位时截断得到的 31 位整数不足以均匀分布
+    pub fn digits(mut self, digits: u32) -> Result<Self> {
+        if !(6..=8).contains(&digits) {
+            return Err(anyhow!(
+                "otp digits must be between 6 and 8, got {}",
+                digits
+            ));
+        }
+        self.digits = digits;
+        Ok(self)
+    }
+
+    pub fn period(mut self, period: u64) -> Result<Self> {
+        if period == 0 {
+            return Err(anyhow!("otp period must be at least 1 second"));
+        }
+        self.period = period;
+        Ok(self)
+    }
+
+    pub fn secret(&self) -> String {
+        BASE32_NOPAD.encode(&self.secret)
+    }
+
+    // 生成认证器使用的 uri, 例如 `otpauth://totp/ACME:alice?secret=...&issuer=ACME`, counter 不为空时生成 hotp
+    pub fn uri(&self, label: &str, issuer: Option<&str>, counter: Option<u64>) -> Result<String> {
+        let kind = if counter.is_some() { "hotp" } else { "totp" };
+        let mut url = Url::parse(&format!("otpauth://{}", kind))?;
+        match issuer {
+            Some(issuer) => url.set_path(&format!("{}:{}", issuer, label)),
+            None => url.set_path(label),
+        }
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("secret", &self.secret());
+            if let Some(issuer) = issuer {
+                query.append_pair("issuer", issuer);
+            }
+            query
+                .append_pair("algorithm", &self.algorithm.to_string())
+                .append_pair("digits", &self.digits.to_string());
+            match counter {
+                Some(counter) => query.append_pair("counter", &counter.to_string()),
+                None => query.append_pair("period", &self.period.to_string()),
+            };
+        }
+        Ok(url.to_string())
+    }
+
+    pub fn hotp(&self, counter: u64) -> String {
+        let key = match self.algorithm {
+            OtpAlgorithm::Sha1 => hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY,
+            OtpAlgorithm::Sha256 => hmac::HMAC_SHA256,
+            OtpAlgorithm::Sha512 => hmac::HMAC_SHA512,
+        };
+        let tag = hmac::sign(&hmac::Key::new(key, &self.secret), &counter.to_be_bytes());
+        let tag = tag.as_ref();
+        // 动态截断: 取最后一个字节的低 4 位作为偏移, 读取 4 个字节并去掉最高位
+        let offset = (tag[tag.len() - 1] & 0x0f) as usize;
+        let code = u32::from_be_bytes([
+            tag[offset] & 0x7f,
+            tag[offset + 1],
+            tag[offset + 2],
+            tag[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            code as u64 % 10u64.pow(self.digits),
+            width = self.digits as usize
+        )
+    }
+
+    pub fn counter(&self, timestamp: u64) -> u64 {
+        timestamp / self.period
+    }
+
+    pub fn totp(&self, timestamp: u64) -> String {
+        self.hotp(self.counter(timestamp))
+    }
+
+    // 当前时间窗口剩余的秒数
+    pub fn remaining(&self, timestamp: u64) -> u64 {
+        self.period - timestamp % self.period
+    }
+
+    // 在 counter 之后 skew 个计数器内验证 (RFC 4226 的 look-ahead 窗口), 返回匹配的偏移
+    // 不接受 counter 之前的计数器, 避免已经使用过的 code 被重放
+    pub fn verify_hotp(&self, code: &str, counter: u64, skew: u64) -> Option<i64> {
+        self.find(code, counter, counter, counter.saturating_add(skew))
+    }
+
+    // 在当前时间窗口前后 skew 个窗口内验证, 返回匹配的窗口偏移
+    pub fn verify_totp(&self, code: &str, timestamp: u64, skew: u64) -> Option<i64> {
+        let counter = self.counter(timestamp);
+        self.find(
+            code,
+            counter,
+            counter.saturating_sub(skew),
+            counter.saturating_add(skew),
+        )
+    }
+
+    fn find(&self, code: &str, counter: u64, start: u64, end: u64) -> Option<i64> {
+        (start..=end)
+            .find(|c| constant_time_eq(self.hotp(*c).as_bytes(), code.as_bytes()))
+            .map(|c| c as i64 - counter as i64)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotp() {
+        // RFC 4226 附录 D
+        let otp = Otp::new(b"12345678901234567890".to_vec());
+        let codes = (0..4).map(|c| otp.hotp(c)).collect::<Vec<_>>();
+        assert_eq!(codes, ["755224", "287082", "359152", "969429"]);
+        assert_eq!(otp.verify_hotp("969429", 1, 2), Some(2));
+        assert_eq!(otp.verify_hotp("969429", 0, 2), None);
+        // 已经使用过的计数器不能再次通过验证
+        assert_eq!(otp.verify_hotp("287082", 2, 2), None);
+    }
+
+    #[test]
+    fn test_totp() -> Result<()> {
+        // RFC 6238 附录 B
+        let cases = [
+            (OtpAlgorithm::Sha1, "12345678901234567890", "94287082"),
+            (
+                OtpAlgorithm::Sha256,
+                "12345678901234567890123456789012",
+                "46119246",
+            ),
+            (
+                OtpAlgorithm::Sha512,
+                "1234567890123456789012345678901234567890123456789012345678901234",
+                "90693936",
+            ),
+        ];
+        for (algorithm, secret, code) in cases {
+            let otp = Otp::new(secret.as_bytes().to_vec())
+                .algorithm(algorithm)
+                .digits(8)?;
+            assert_eq!(otp.totp(59), code);
+            assert_eq!(otp.verify_totp(code, 59 + 30, 1), Some(-1));
+            assert_eq!(otp.verify_totp(code, 59 + 30, 0), None);
+        }
+        let otp = Otp::new(b"12345678901234567890".to_vec()).digits(8)?;
+        assert_eq!(otp.totp(1111111109), "07081804");
+        assert_eq!(otp.remaining(59), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_uri() -> Result<()> {
+        let otp = Otp::from_base32("gezd gnbv gy3t qojq")?.algorithm(OtpAlgorithm::Sha256);
+        assert_eq!(otp.secret(), "GEZDGNBVGY3TQOJQ");
+        let uri = otp.uri("alice@example.com", Some("ACME Co"), None)?;
+        assert_eq!(
+            uri,
+            "otpauth://totp/ACME%20Co:alice@example.com?secret=GEZDGNBVGY3TQOJQ&issuer=ACME+Co&algorithm=SHA256&digits=6&period=30"
+        );
+        let (parsed, counter) = Otp::from_uri(&uri)?;
+        assert_eq!(counter, None);
+        assert_eq!(parsed.totp(59), otp.totp(59));
+
+        let uri = otp.digits(8)?.uri("alice", None, Some(5))?;
+        let (parsed, counter) = Otp::from_uri(&uri)?;
+        assert_eq!(counter, Some(5));
+        assert_eq!(parsed.hotp(5).len(), 8);
+        assert!(Otp::from_uri("https://example.com/totp").is_err());
+        assert!(Otp::from_base32("not base32!").is_err());
+        for uri in [
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&period=0",
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&digits=20",
+            "otpauth://totp/x?secret=JBSWY3DPEHPK3PXP&digits=4",
+        ] {
+            assert!(Otp::from_uri(uri).is_err(), "{}", uri);
+        }
+        Ok(())
+    }
+}
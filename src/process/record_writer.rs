@@ -0,0 +1,493 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use unicode_width::UnicodeWidthStr;
+
+use crate::cli::OutputFormat;
+
+// 逐条写出记录的 writer, 每条记录写出后即可释放, 不需要将所有记录缓存在内存中
+pub trait RecordWriter {
+    fn write(&mut self, record: &Value) -> Result<()>;
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+// 根据 format 构造不同的 RecordWriter
+pub fn record_writer<'a>(
+    format: OutputFormat,
+    writer: Box<dyn Write + 'a>,
+) -> Box<dyn RecordWriter + 'a> {
+    match format {
+        OutputFormat::Json => Box::new(JsonWriter::new(writer)),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter::new(writer)),
+        OutputFormat::Yaml => Box::new(YamlWriter::new(writer)),
+        OutputFormat::Toml => Box::new(TomlWriter::new(writer)),
+        OutputFormat::Xml => Box::new(XmlWriter::new(writer)),
+        OutputFormat::Markdown => Box::new(TableWriter::new(writer, TableStyle::Markdown)),
+        OutputFormat::Table => Box::new(TableWriter::new(writer, TableStyle::Ascii)),
+    }
+}
+
+// json 数组, 每个元素按照 pretty 格式缩进写出, 与 to_string_pretty 一样末尾没有换行
+struct JsonWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> JsonWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, count: 0 }
+    }
+}
+
+impl<W: Write> RecordWriter for JsonWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        let sep = if self.count == 0 { "[\n  " } else { ",\n  " };
+        // json 字符串中的换行都会被转义, 因此可以直接按行缩进
+        let element = serde_json::to_string_pretty(record)?.replace('\n', "\n  ");
+        self.writer.write_all(sep.as_bytes())?;
+        self.writer.write_all(element.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        let end = if self.count == 0 { "[]" } else { "\n]" };
+        self.writer.write_all(end.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// 每行一个 json 对象
+struct NdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> RecordWriter for NdjsonWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// yaml 序列, 每条记录作为一个 `- ` 元素写出, 拼接结果与整体序列化一致
+struct YamlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> YamlWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, count: 0 }
+    }
+}
+
+impl<W: Write> RecordWriter for YamlWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        let element = serde_yaml::to_string(std::slice::from_ref(record))?;
+        self.writer.write_all(element.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.count == 0 {
+            self.writer.write_all(b"[]\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// toml 顶层不能是数组, 每条记录写成一个 [[records]] 表, toml 不支持 null, 因此 null 字段会被省略
+// 数组中的 null 不能省略, 否则后面元素的下标会改变, 因此直接报错
+struct TomlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> TomlWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, count: 0 }
+    }
+}
+
+impl<W: Write> RecordWriter for TomlWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        if self.count > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        let table = toml::to_string(&json!({ "records": [strip_nulls(record)?] }))?;
+        self.writer.write_all(table.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn strip_nulls(value: &Value) -> Result<Value> {
+    let value = match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok((k.clone(), strip_nulls(v)?)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| match v {
+                    Value::Null => Err(anyhow!("toml does not support null array items")),
+                    v => strip_nulls(v),
+                })
+                .collect::<Result<_>>()?,
+        ),
+        v => v.clone(),
+    };
+    Ok(value)
+}
+
+// xml 文档, 根节点为 <records>, 每条记录为一个 <record>, 嵌套对象为子节点, 数组为同名的重复节点
+struct XmlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> XmlWriter<W> {
+    fn new(writer: W) -> Self {
+        Self { writer, count: 0 }
+    }
+
+    fn write_value(&mut self, name: &str, value: &Value, depth: usize) -> Result<()> {
+        let indent = "  ".repeat(depth);
+        let tag = xml_name(name);
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    self.write_value(name, item, depth)?;
+                }
+            }
+            Value::Object(map) => {
+                writeln!(self.writer, "{}<{}>", indent, tag)?;
+                for (k, v) in map {
+                    self.write_value(k, v, depth + 1)?;
+                }
+                writeln!(self.writer, "{}</{}>", indent, tag)?;
+            }
+            Value::Null => writeln!(self.writer, "{}<{}/>", indent, tag)?,
+            Value::String(s) => writeln!(
+                self.writer,
+                "{}<{}>{}</{}>",
+                indent,
+                tag,
+                xml_escape(s),
+                tag
+            )?,
+            v => writeln!(self.writer, "{}<{}>{}</{}>", indent, tag, v, tag)?,
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for XmlWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        if self.count == 0 {
+            self.writer
+                .write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records>\n")?;
+        }
+        self.write_value("record", record, 1)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.count == 0 {
+            self.writer
+                .write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records/>\n")?;
+        } else {
+            self.writer.write_all(b"</records>\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// 将列名转换为合法的 xml 元素名, 非法字符替换为 _
+fn xml_name(name: &str) -> String {
+    let mut tag = name
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    if !tag.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        tag.insert(0, '_');
+    }
+    tag
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableStyle {
+    Markdown,
+    Ascii,
+}
+
+// 表格需要根据所有记录计算列宽 (按终端显示宽度, 汉字占两列), 因此是唯一需要缓存记录的格式, 适合粘贴到文档中的小数据量
+// markdown 表格用于渲染, ascii 表格带有 `+---+` 边框, 适合在终端或纯文本中查看
+struct TableWriter<W> {
+    writer: W,
+    style: TableStyle,
+    columns: Vec<String>,
+    rows: Vec<Map<String, Value>>,
+}
+
+impl<W: Write> TableWriter<W> {
+    fn new(writer: W, style: TableStyle) -> Self {
+        Self {
+            writer,
+            style,
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn write_row(&mut self, cells: &[String], widths: &[usize]) -> Result<()> {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!(" {}{} ", cell, " ".repeat(width - cell.width())))
+            .collect::<Vec<_>>()
+            .join("|");
+        writeln!(self.writer, "|{}|", line)?;
+        Ok(())
+    }
+
+    fn write_border(&mut self, widths: &[usize]) -> Result<()> {
+        let line = widths
+            .iter()
+            .map(|w| "-".repeat(w + 2))
+            .collect::<Vec<_>>()
+            .join("+");
+        writeln!(self.writer, "+{}+", line)?;
+        Ok(())
+    }
+
+    fn cell(&self, value: Option<&Value>) -> String {
+        let s = match value {
+            None | Some(Value::Null) => return String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(v) => v.to_string(),
+        };
+        self.escape(&s)
+    }
+
+    fn escape(&self, s: &str) -> String {
+        match self.style {
+            TableStyle::Markdown => s
+                .replace('|', "\\|")
+                .replace("\r\n", "<br>")
+                .replace('\n', "<br>"),
+            TableStyle::Ascii => s.replace("\r\n", " ").replace(['\n', '\t'], " "),
+        }
+    }
+}
+
+impl<W: Write> RecordWriter for TableWriter<W> {
+    fn write(&mut self, record: &Value) -> Result<()> {
+        let row = match record {
+            Value::Object(map) => map.clone(),
+            v => Map::from_iter([("value".to_string(), v.clone())]),
+        };
+        for key in row.keys() {
+            if !self.columns.contains(key) {
+                self.columns.push(key.clone());
+            }
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        if self.columns.is_empty() {
+            return Ok(());
+        }
+        let header = self
+            .columns
+            .iter()
+            .map(|c| self.escape(c))
+            .collect::<Vec<_>>();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|c| self.cell(row.get(c)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].width())
+                    .chain([header[i].width(), 3])
+                    .max()
+                    .unwrap_or(3)
+            })
+            .collect::<Vec<_>>();
+        match self.style {
+            TableStyle::Markdown => {
+                let separator = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+                self.write_row(&header, &widths)?;
+                self.write_row(&separator, &widths)?;
+                for row in &rows {
+                    self.write_row(row, &widths)?;
+                }
+            }
+            TableStyle::Ascii => {
+                self.write_border(&widths)?;
+                self.write_row(&header, &widths)?;
+                self.write_border(&widths)?;
+                for row in &rows {
+                    self.write_row(row, &widths)?;
+                }
+                self.write_border(&widths)?;
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(format: OutputFormat, records: &[Value]) -> Result<String> {
+        let mut buf = Vec::new();
+        let mut writer = record_writer(format, Box::new(&mut buf));
+        for record in records {
+            writer.write(record)?;
+        }
+        writer.finish()?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn test_streaming_matches_buffered_output() -> Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "kit": "77"}),
+            json!({"name": "Perin", "kit": "37"}),
+        ];
+        assert_eq!(
+            render(OutputFormat::Json, &records)?,
+            serde_json::to_string_pretty(&records)?
+        );
+        assert_eq!(
+            render(OutputFormat::Yaml, &records)?,
+            serde_yaml::to_string(&records)?
+        );
+        assert_eq!(
+            render(OutputFormat::Ndjson, &records)?,
+            "{\"name\":\"Buffon\",\"kit\":\"77\"}\n{\"name\":\"Perin\",\"kit\":\"37\"}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_output() -> Result<()> {
+        assert_eq!(render(OutputFormat::Json, &[])?, "[]");
+        assert_eq!(render(OutputFormat::Yaml, &[])?, "[]\n");
+        assert_eq!(render(OutputFormat::Ndjson, &[])?, "");
+        assert_eq!(render(OutputFormat::Toml, &[])?, "");
+        assert_eq!(render(OutputFormat::Markdown, &[])?, "");
+        assert_eq!(render(OutputFormat::Table, &[])?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_output() -> Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "kit": 77, "note": null}),
+            json!({"name": "Perin", "kit": 37, "note": "loan"}),
+        ];
+        assert_eq!(
+            render(OutputFormat::Toml, &records)?,
+            "[[records]]\nname = \"Buffon\"\nkit = 77\n\n[[records]]\nname = \"Perin\"\nkit = 37\nnote = \"loan\"\n"
+        );
+        // 数组中的 null 不能省略, 否则会改变后面元素的下标
+        assert!(render(OutputFormat::Toml, &[json!({"tags": [1, null, 3]})]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xml_output() -> Result<()> {
+        let records =
+            vec![json!({"Kit Number": 1, "name": "A&B", "tags": ["x", "y"], "note": null})];
+        assert_eq!(
+            render(OutputFormat::Xml, &records)?,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records>\n  <record>\n    <Kit_Number>1</Kit_Number>\n    <name>A&amp;B</name>\n    <tags>x</tags>\n    <tags>y</tags>\n    <note/>\n  </record>\n</records>\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_markdown_output() -> Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "kit": 77}),
+            json!({"name": "a|b", "note": "loan"}),
+        ];
+        assert_eq!(
+            render(OutputFormat::Markdown, &records)?,
+            "| name   | kit | note |\n| ------ | --- | ---- |\n| Buffon | 77  |      |\n| a\\|b   |     | loan |\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_wide_chars() -> Result<()> {
+        let records = vec![json!({"name": "布冯", "kit": 1}), json!({"name": "Perin"})];
+        assert_eq!(
+            render(OutputFormat::Markdown, &records)?,
+            "| name  | kit |\n| ----- | --- |\n| 布冯  | 1   |\n| Perin |     |\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ascii_table_output() -> Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "kit": 77}),
+            json!({"name": "a|b", "note": "on\nloan"}),
+        ];
+        assert_eq!(
+            render(OutputFormat::Table, &records)?,
+            "+--------+-----+---------+\n| name   | kit | note    |\n+--------+-----+---------+\n| Buffon | 77  |         |\n| a|b    |     | on loan |\n+--------+-----+---------+\n"
+        );
+        Ok(())
+    }
+}
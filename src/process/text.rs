@@ -29,7 +29,7 @@ trait Decrypt {
     fn decrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
 }
 
-struct Blake3 {
+pub(crate) struct Blake3 {
     key: [u8; 32],
 }
 
@@ -46,6 +46,17 @@ impl Blake3 {
             .map_err(|_| anyhow!("key must be 32 bytes"))?;
         Ok(Self::new(key))
     }
+
+    pub fn hash(&self, data: &[u8]) -> blake3::Hash {
+        blake3::keyed_hash(&self.key, data)
+    }
+
+    // 任意长度的 keyed hash 输出, 用于 csv mask 生成确定性的替换值
+    pub fn hash_xof(&self, data: &[u8]) -> blake3::OutputReader {
+        let mut hasher = blake3::Hasher::new_keyed(&self.key);
+        hasher.update(data);
+        hasher.finalize_xof()
+    }
 }
 
 impl TextSign for Blake3 {
@@ -53,8 +64,7 @@ impl TextSign for Blake3 {
         // TODO: improve perf by reading in chunks
         let mut data = Vec::new();
         reader.read_to_end(&mut data)?;
-        let hash = blake3::keyed_hash(&self.key, &data);
-        Ok(hash.as_bytes().to_vec())
+        Ok(self.hash(&data).as_bytes().to_vec())
     }
 }
 
@@ -74,8 +84,7 @@ impl TextVerify for Blake3 {
     fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
         let mut data = Vec::new();
         reader.read_to_end(&mut data)?;
-        let hash = blake3::keyed_hash(&self.key, &data);
-        Ok(hash.as_bytes() == sig)
+        Ok(self.hash(&data).as_bytes() == sig)
     }
 }
 
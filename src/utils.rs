@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::io::Read;
+use std::io::{BufWriter, Read, Write};
 
 // 提取 get_reader 函数, 用于根据输入的文件路径或者 - 来获取 Reader
 pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
@@ -15,3 +15,11 @@ pub fn get_content(input: &str) -> Result<Vec<u8>> {
     reader.read_to_end(&mut content)?;
     Ok(content)
 }
+
+// 根据输出文件路径或者 - 来获取 Writer
+pub fn get_writer(output: &str) -> Result<Box<dyn Write>> {
+    match output {
+        "-" => Ok(Box::new(BufWriter::new(std::io::stdout()))),
+        path => Ok(Box::new(BufWriter::new(std::fs::File::create(path)?))),
+    }
+}